@@ -1,4 +1,4 @@
-use docfind_core::Document;
+use docfind_core::{Document, IndexOptions};
 use std::io::Write;
 use std::path::Path;
 use std::{collections::HashMap, fs::File};
@@ -84,23 +84,65 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 		std::process::exit(0);
 	}
 
-	if args.len() != 3 {
-		eprintln!("Usage: {} <documents.json> <outdir>", args[0]);
+	// Split `--config <options.json>`, `--stop-words <file>` and
+	// `--synonyms <file>` from the positional arguments
+	let mut config_path: Option<&String> = None;
+	let mut stop_words_path: Option<&String> = None;
+	let mut synonyms_path: Option<&String> = None;
+	let mut positional: Vec<&String> = Vec::new();
+	let mut rest = args.iter().skip(1);
+	while let Some(arg) = rest.next() {
+		let value = match arg.as_str() {
+			"--config" => &mut config_path,
+			"--stop-words" => &mut stop_words_path,
+			"--synonyms" => &mut synonyms_path,
+			_ => {
+				positional.push(arg);
+				continue;
+			}
+		};
+		*value = rest.next();
+		if value.is_none() {
+			eprintln!("Missing value for {}", arg);
+			std::process::exit(1);
+		}
+	}
+
+	if positional.len() != 2 {
+		eprintln!(
+			"Usage: {} [--config <options.json>] [--stop-words <file>] [--synonyms <file>] <documents.json> <outdir>",
+			args[0]
+		);
 		std::process::exit(1);
 	}
 
-	let input_path = &args[1];
-	let output_dir = &args[2];
+	let input_path = positional[0];
+	let output_dir = positional[1];
+	let mut options: IndexOptions = match config_path {
+		Some(path) => serde_json::from_reader(File::open(path)?)?,
+		None => IndexOptions::default(),
+	};
+	if let Some(path) = stop_words_path {
+		let list = std::fs::read_to_string(path)?;
+		options
+			.stop_words
+			.extend(docfind_core::parse_stop_words(&list));
+	}
+	if let Some(path) = synonyms_path {
+		let list = std::fs::read_to_string(path)?;
+		options.synonyms.extend(docfind_core::parse_synonyms(&list));
+	}
 	if debug {
 		eprintln!("[docfind] CWD: {:?}", std::env::current_dir()?);
 		eprintln!("[docfind] input_path: {}", input_path);
 		eprintln!("[docfind] output_dir: {}", output_dir);
+		eprintln!("[docfind] options: {:?}", options);
 	}
 	let documents_file = File::open(input_path)?;
 	let documents: Vec<Document> = serde_json::from_reader(documents_file)?;
 
 	let start = std::time::Instant::now();
-	let index = docfind_core::build_index(documents)?;
+	let index = docfind_core::build_index_with_options(documents, &options)?;
 	let duration = start.elapsed();
 	if debug {
 		eprintln!("[docfind] Indexing completed in: {:?}", duration);
@@ -168,7 +210,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 			sections.push(WasmSection::DataCount(count));
 		} else if let Payload::MemorySection(reader) = payload {
 			for memory in reader {
-				old_memory_page_count = memory?.initial as u64;
+				old_memory_page_count = memory?.initial;
 			}
 			sections.push(WasmSection::Memory);
 		} else {
@@ -195,10 +237,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 						let global = global?;
 						let mut ops_reader = global.init_expr.get_operators_reader();
 
-						if !ops_reader.eof() {
-							if let Ok(wasmparser::Operator::I32Const { value }) = ops_reader.read() {
-								i32_globals.insert(idx as u32, value);
-							}
+						if !ops_reader.eof()
+							&& let Ok(wasmparser::Operator::I32Const { value }) = ops_reader.read()
+						{
+							i32_globals.insert(idx as u32, value);
 						}
 					}
 				}
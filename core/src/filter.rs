@@ -0,0 +1,213 @@
+use crate::{FieldKind, IndexView, read_f64, read_u32};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Comparison {
+	Equal,
+	NotEqual,
+	Less,
+	LessOrEqual,
+	Greater,
+	GreaterOrEqual,
+}
+
+impl Comparison {
+	fn from_operator(operator: &str) -> Option<Comparison> {
+		match operator {
+			"=" | "==" => Some(Comparison::Equal),
+			"!=" => Some(Comparison::NotEqual),
+			"<" => Some(Comparison::Less),
+			"<=" => Some(Comparison::LessOrEqual),
+			">" => Some(Comparison::Greater),
+			">=" => Some(Comparison::GreaterOrEqual),
+			_ => None,
+		}
+	}
+
+	/// Compare a document value, which is NaN when the document has none and
+	/// then only differs from every value.
+	fn compare(self, value: f64, other: f64) -> bool {
+		match self {
+			Comparison::Equal => value == other,
+			Comparison::NotEqual => value != other,
+			Comparison::Less => value < other,
+			Comparison::LessOrEqual => value <= other,
+			Comparison::Greater => value > other,
+			Comparison::GreaterOrEqual => value >= other,
+		}
+	}
+}
+
+/// Whether the document at an index matches a compiled condition.
+type Predicate<'a> = Box<dyn Fn(usize) -> bool + 'a>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Condition {
+	pub field: String,
+	pub comparison: Comparison,
+	pub value: String,
+}
+
+/// A parsed filter expression: documents match when they match every condition
+/// of one of the groups.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Filter {
+	pub groups: Vec<Vec<Condition>>,
+}
+
+/// Parse a filter expression over the filterable custom fields.
+///
+/// Supported syntax:
+/// - `field = value` or `field != value` for any filterable field
+/// - `field < value`, `<=`, `>` and `>=` for number, date and version fields,
+///   where dates are written as `2024-05-31` and versions as `3.10`
+/// - `"quoted values"` for values with spaces
+/// - conditions joined with `AND` and `OR`, where `AND` binds tighter
+pub(crate) fn parse(expression: &str) -> Result<Filter, Box<dyn std::error::Error>> {
+	let is_operator = |c: &char| matches!(c, '<' | '>' | '=' | '!');
+	let mut chars = expression.chars().peekable();
+	let mut groups: Vec<Vec<Condition>> = vec![Vec::new()];
+
+	loop {
+		while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+		let mut field = String::new();
+		while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !is_operator(c)) {
+			field.push(c);
+		}
+		while chars.next_if(|c| c.is_whitespace()).is_some() {}
+		let mut operator = String::new();
+		while let Some(c) = chars.next_if(is_operator) {
+			operator.push(c);
+		}
+		while chars.next_if(|c| c.is_whitespace()).is_some() {}
+		let mut value = String::new();
+		if chars.next_if_eq(&'"').is_some() {
+			while let Some(c) = chars.next_if(|c| *c != '"') {
+				value.push(c);
+			}
+			if chars.next().is_none() {
+				return Err(format!("Unterminated quote in filter '{}'", expression).into());
+			}
+		} else {
+			while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+				value.push(c);
+			}
+		}
+
+		let comparison = Comparison::from_operator(&operator)
+			.filter(|_| !field.is_empty() && !value.is_empty())
+			.ok_or_else(|| format!("Invalid condition in filter '{}'", expression))?;
+		groups.last_mut().unwrap().push(Condition {
+			field,
+			comparison,
+			value,
+		});
+
+		while chars.next_if(|c| c.is_whitespace()).is_some() {}
+		let mut joiner = String::new();
+		while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+			joiner.push(c);
+		}
+		match joiner.as_str() {
+			"" => break,
+			"AND" => {}
+			"OR" => groups.push(Vec::new()),
+			_ => return Err(format!("Expected AND or OR in filter '{}'", expression).into()),
+		}
+	}
+
+	Ok(Filter { groups })
+}
+
+impl Filter {
+	/// Resolve the fields and values of the filter against an index, returning
+	/// whether a document matches.
+	pub(crate) fn compile<'a>(
+		&self,
+		index: &IndexView<'a>,
+	) -> Result<impl Fn(usize) -> bool + 'a, Box<dyn std::error::Error>> {
+		let mut groups: Vec<Vec<Predicate<'a>>> = Vec::new();
+		for group in &self.groups {
+			let mut conditions: Vec<Predicate<'a>> = Vec::new();
+			for condition in group {
+				conditions.push(compile_condition(index, condition)?);
+			}
+			groups.push(conditions);
+		}
+		Ok(move |document_index| {
+			groups
+				.iter()
+				.any(|group| group.iter().all(|condition| condition(document_index)))
+		})
+	}
+}
+
+fn compile_condition<'a>(
+	index: &IndexView<'a>,
+	condition: &Condition,
+) -> Result<Predicate<'a>, Box<dyn std::error::Error>> {
+	let comparison = condition.comparison;
+
+	if let Some(attribute) = index
+		.attributes
+		.iter()
+		.find(|attribute| attribute.name == condition.field)
+	{
+		let kind = attribute.kind;
+		let invalid = || {
+			format!(
+				"Invalid value '{}' for {:?} field '{}'",
+				condition.value, kind, condition.field
+			)
+		};
+		let value = if kind == FieldKind::Version {
+			// place the version among the stored ones, between two positions when
+			// no document has it
+			let version = FieldKind::version(&condition.value).ok_or_else(invalid)?;
+			match attribute
+				.versions
+				.binary_search_by(|stored| FieldKind::version(stored).cmp(&Some(version.clone())))
+			{
+				Ok(position) => position as f64,
+				Err(position) => position as f64 - 0.5,
+			}
+		} else {
+			kind.parse(&condition.value).ok_or_else(invalid)?
+		};
+		let values = attribute.values;
+		return Ok(Box::new(move |document_index| {
+			comparison.compare(read_f64(values, document_index), value)
+		}));
+	}
+
+	if let Some(field) = index
+		.filter_fields
+		.iter()
+		.find(|field| field.name == condition.field)
+	{
+		let equal = match comparison {
+			Comparison::Equal => true,
+			Comparison::NotEqual => false,
+			_ => {
+				return Err(
+					format!(
+						"Field '{}' can only be compared with = and !=",
+						condition.field
+					)
+					.into(),
+				);
+			}
+		};
+		let position = field
+			.values
+			.binary_search(&condition.value.as_str())
+			.ok()
+			.map(|position| position as u32);
+		let documents = field.documents;
+		return Ok(Box::new(move |document_index| {
+			(position == Some(read_u32(documents, document_index))) == equal
+		}));
+	}
+
+	Err(format!("Field '{}' is not filterable", condition.field).into())
+}
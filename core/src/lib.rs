@@ -1,17 +1,55 @@
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
 
 #[cfg(any(feature = "cli", feature = "wasm", test))]
 use std::collections::HashMap;
 
+#[cfg(any(feature = "wasm", test))]
+mod filter;
+#[cfg(any(feature = "wasm", test))]
+mod query;
+#[cfg(any(feature = "wasm", test))]
+mod snippet;
+#[cfg(any(feature = "cli", feature = "wasm", test))]
+mod stem;
+#[cfg(any(feature = "cli", feature = "wasm", test))]
+mod tokenize;
+
+/// Read the `i`-th little-endian `u32` of a byte slice.
+fn read_u32(bytes: &[u8], i: usize) -> u32 {
+	u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap())
+}
+
+/// Read the `i`-th little-endian `f64` of a byte slice.
+#[cfg(any(feature = "wasm", test))]
+fn read_f64(bytes: &[u8], i: usize) -> f64 {
+	f64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap())
+}
+
+/// FSST code announcing that the next byte is stored literally.
+const ESCAPE_CODE: u8 = 255;
+
+/// Decode an FSST symbol table stored as 8 raw bytes per symbol.
+fn decode_symbols(dict_syms: &[u8]) -> Vec<fsst::Symbol> {
+	dict_syms
+		.chunks_exact(8)
+		.map(|sym| fsst::Symbol::from_slice(sym.try_into().unwrap()))
+		.collect()
+}
+
 /// A minimal FSST-compressed vector of UTF-8 strings with random access.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone)]
 pub struct FsstStrVec {
-	// FSST dictionary we trained (as raw bytes for compact serde)
-	dict_syms: Vec<[u8; 8]>,
+	// FSST dictionary we trained (as raw bytes, 8 per symbol, for compact serde)
+	dict_syms: Vec<u8>,
 	dict_lens: Vec<u8>,
 	// Concatenated compressed payload and per-item offsets
-	offsets: Vec<u32>, // offsets[i] = start of item i in `data`
+	offsets: Vec<u8>, // little-endian u32 per item, the start of item i in `data`
 	data: Vec<u8>,
+	// Symbol table decoded from `dict_syms`, built on first access and reused
+	symbols: OnceLock<Vec<fsst::Symbol>>,
 }
 
 impl FsstStrVec {
@@ -27,18 +65,18 @@ impl FsstStrVec {
 		let lens: Vec<u8> = compressor.symbol_lengths().to_vec();
 
 		// 2) Compress each string independently; store offsets + bytes.
-		let mut offsets = Vec::with_capacity(strings.len());
+		let mut offsets = Vec::with_capacity(strings.len() * 4);
 		let mut data = Vec::new();
 		for s in strings {
-			offsets.push(data.len() as u32);
+			offsets.extend_from_slice(&(data.len() as u32).to_le_bytes());
 			let c = compressor.compress(s.as_ref().as_bytes());
 			data.extend_from_slice(&c);
 		}
 
 		// 3) Store symbol table as raw bytes for compact serialization.
-		let dict_syms: Vec<[u8; 8]> = syms
+		let dict_syms: Vec<u8> = syms
 			.into_iter()
-			.map(|sym| u64::to_le_bytes(sym.to_u64()))
+			.flat_map(|sym| u64::to_le_bytes(sym.to_u64()))
 			.collect();
 
 		Self {
@@ -46,12 +84,108 @@ impl FsstStrVec {
 			dict_lens: lens,
 			offsets,
 			data,
+			symbols: OnceLock::new(),
+		}
+	}
+
+	/// Borrow the vector, reusing its decoded symbol table.
+	pub fn view(&self) -> FsstStrView<'_> {
+		let symbols = self.symbols.get_or_init(|| decode_symbols(&self.dict_syms));
+		FsstStrView {
+			dict_syms: &self.dict_syms,
+			dict_lens: &self.dict_lens,
+			offsets: &self.offsets,
+			data: &self.data,
+			symbols: Cow::Borrowed(symbols),
+		}
+	}
+
+	/// Number of strings
+	pub fn len(&self) -> usize {
+		self.offsets.len() / 4
+	}
+
+	/// Whether the vector holds no strings
+	pub fn is_empty(&self) -> bool {
+		self.offsets.is_empty()
+	}
+
+	/// Random access: decode item i into an owned String.
+	pub fn get(&self, i: usize) -> Option<String> {
+		self.view().get(i)
+	}
+}
+
+/// An [`FsstStrVec`] read in place, e.g. from the bytes of a serialized index.
+#[derive(Debug, Clone, Serialize)]
+pub struct FsstStrView<'a> {
+	dict_syms: &'a [u8],
+	dict_lens: &'a [u8],
+	offsets: &'a [u8],
+	data: &'a [u8],
+	// The Decompressor itself only borrows the symbol table, which is decoded
+	// once since search decodes several strings per hit.
+	#[serde(skip)]
+	symbols: Cow<'a, [fsst::Symbol]>,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for FsstStrView<'a> {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		#[derive(Deserialize)]
+		struct Raw<'a> {
+			dict_syms: &'a [u8],
+			dict_lens: &'a [u8],
+			offsets: &'a [u8],
+			data: &'a [u8],
+		}
+
+		let raw = Raw::deserialize(deserializer)?;
+		let symbol_count = raw.dict_lens.len();
+		let offsets: Vec<usize> = (0..raw.offsets.len() / 4)
+			.map(|i| read_u32(raw.offsets, i) as usize)
+			.chain(std::iter::once(raw.data.len()))
+			.collect();
+		// the decompressor looks symbols up without bounds checks, so every code
+		// must refer to one, or be an escape followed by a literal byte
+		let valid_codes = |mut codes: &[u8]| loop {
+			match codes {
+				[] => return true,
+				[ESCAPE_CODE, _, rest @ ..] => codes = rest,
+				[code, rest @ ..] if (*code as usize) < symbol_count => codes = rest,
+				_ => return false,
+			}
+		};
+		if raw.dict_syms.len() != symbol_count * 8
+			|| symbol_count >= ESCAPE_CODE as usize
+			|| raw.dict_lens.iter().any(|len| !(1..=8).contains(len))
+			|| !raw.offsets.len().is_multiple_of(4)
+			|| !offsets.is_sorted()
+			|| !offsets
+				.windows(2)
+				.all(|window| valid_codes(&raw.data[window[0]..window[1]]))
+		{
+			return Err(serde::de::Error::custom("malformed FSST string vector"));
 		}
+
+		Ok(FsstStrView {
+			dict_syms: raw.dict_syms,
+			dict_lens: raw.dict_lens,
+			offsets: raw.offsets,
+			data: raw.data,
+			symbols: Cow::Owned(decode_symbols(raw.dict_syms)),
+		})
 	}
+}
 
+impl FsstStrView<'_> {
 	/// Number of strings
 	pub fn len(&self) -> usize {
-		self.offsets.len()
+		self.offsets.len() / 4
+	}
+
+	/// Whether the vector holds no strings
+	pub fn is_empty(&self) -> bool {
+		self.offsets.is_empty()
 	}
 
 	/// Random access: decode item i into an owned String.
@@ -59,29 +193,22 @@ impl FsstStrVec {
 		if i >= self.len() {
 			return None;
 		}
-		let start = self.offsets[i] as usize;
+		let start = read_u32(self.offsets, i) as usize;
 		let end = if i + 1 < self.len() {
-			self.offsets[i + 1] as usize
+			read_u32(self.offsets, i + 1) as usize
 		} else {
 			self.data.len()
 		};
-		let codes = &self.data[start..end];
+		let codes = self.data.get(start..end)?;
 
-		// Rebuild a Decompressor on-demand. (You can cache this in the struct if you
-		// read frequently; it's cheap either way.)
-		let syms: Vec<fsst::Symbol> = self
-			.dict_syms
-			.iter()
-			.map(fsst::Symbol::from_slice)
-			.collect();
-		let decomp = fsst::Decompressor::new(&syms, &self.dict_lens);
+		let decomp = fsst::Decompressor::new(&self.symbols, self.dict_lens);
 
 		let bytes = decomp.decompress(codes);
-		Some(String::from_utf8(bytes).expect("FSST preserves UTF-8 for UTF-8 input"))
+		String::from_utf8(bytes).ok()
 	}
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Document {
 	pub title: String,
@@ -89,255 +216,2500 @@ pub struct Document {
 	pub href: String,
 	pub body: String,
 	pub keywords: Option<Vec<String>>,
+	/// Language of the document as an ISO 639-1 code such as `de`, which selects
+	/// its stop words. Defaults to [`IndexOptions::language`].
+	pub language: Option<String>,
+	/// Custom fields such as `author` or `product`, indexed as declared by
+	/// [`IndexOptions::fields`]. Numbers and booleans are read as their text.
+	#[serde(
+		default,
+		skip_serializing_if = "BTreeMap::is_empty",
+		deserialize_with = "deserialize_fields"
+	)]
+	pub fields: BTreeMap<String, String>,
+	/// How popular the document is, e.g. its page views. More popular documents
+	/// rank higher for the same keywords, see [`SearchOptions::popularity_weight`].
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub popularity: Option<f64>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
-pub struct Index {
-	/// FST vector for keyword to entry index
-	fst: Vec<u8>,
+/// Read [`Document::fields`], accepting numbers and booleans as well as strings,
+/// e.g. `{"version": 3.2, "beta": true}` from a JSON documents file.
+fn deserialize_fields<'de, D: serde::Deserializer<'de>>(
+	deserializer: D,
+) -> Result<BTreeMap<String, String>, D::Error> {
+	struct Value(String);
 
-	/// FSST string vector of all document strings
-	document_strings: FsstStrVec,
+	impl<'de> Deserialize<'de> for Value {
+		fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+			deserializer.deserialize_any(ValueVisitor)
+		}
+	}
+
+	struct ValueVisitor;
+
+	impl serde::de::Visitor<'_> for ValueVisitor {
+		type Value = Value;
+
+		fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+			formatter.write_str("a string, number or boolean")
+		}
+
+		fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Value, E> {
+			Ok(Value(value.to_string()))
+		}
+
+		fn visit_string<E: serde::de::Error>(self, value: String) -> Result<Value, E> {
+			Ok(Value(value))
+		}
+
+		fn visit_bool<E: serde::de::Error>(self, value: bool) -> Result<Value, E> {
+			Ok(Value(value.to_string()))
+		}
+
+		fn visit_i64<E: serde::de::Error>(self, value: i64) -> Result<Value, E> {
+			Ok(Value(value.to_string()))
+		}
 
-	/// Vector of keyword to document index entries
-	keyword_to_documents: Vec<Vec<(usize, u8)>>,
+		fn visit_u64<E: serde::de::Error>(self, value: u64) -> Result<Value, E> {
+			Ok(Value(value.to_string()))
+		}
+
+		fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<Value, E> {
+			Ok(Value(value.to_string()))
+		}
+	}
+
+	let fields = BTreeMap::<String, Value>::deserialize(deserializer)?;
+	Ok(
+		fields
+			.into_iter()
+			.map(|(name, Value(value))| (name, value))
+			.collect(),
+	)
 }
 
-impl Index {
-	pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
-		let index: Index = postcard::from_bytes(bytes)?;
-		Ok(index)
+/// A document field that index keywords are extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Field {
+	/// Explicit keywords from document metadata
+	Keywords,
+	Title,
+	Category,
+	Href,
+	Body,
+	/// Searchable custom fields, see [`FieldSchema`]
+	Custom,
+}
+
+impl Field {
+	/// All fields, in the order of their bits
+	const ALL: [Field; 6] = [
+		Field::Keywords,
+		Field::Title,
+		Field::Category,
+		Field::Href,
+		Field::Body,
+		Field::Custom,
+	];
+
+	/// Fields matched by queries without a field prefix. Category and href keywords
+	/// are only matched when asked for, e.g. `category:api`.
+	#[cfg(any(feature = "wasm", test))]
+	const DEFAULT_MASK: u8 =
+		Field::Keywords.bit() | Field::Title.bit() | Field::Body.bit() | Field::Custom.bit();
+
+	#[cfg(any(feature = "cli", feature = "wasm", test))]
+	const fn bit(self) -> u8 {
+		1 << self as u8
 	}
 
-	pub fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-		Ok(postcard::to_allocvec(self)?)
+	/// Look up a field by its query prefix name, e.g. `title`.
+	pub fn from_name(name: &str) -> Option<Field> {
+		match name.to_lowercase().as_str() {
+			"keywords" => Some(Field::Keywords),
+			"title" => Some(Field::Title),
+			"category" => Some(Field::Category),
+			"href" => Some(Field::Href),
+			"body" => Some(Field::Body),
+			"custom" => Some(Field::Custom),
+			_ => None,
+		}
+	}
+
+	/// Split the text of this field into normalized words.
+	#[cfg(any(feature = "cli", feature = "wasm", test))]
+	fn words(self, text: &str) -> Vec<String> {
+		match self {
+			// paths keep dots inside words, e.g. `index.html`, split on all punctuation
+			Field::Href => text
+				.split(|c: char| !c.is_alphanumeric())
+				.flat_map(tokenize::words)
+				.collect(),
+			_ => tokenize::words(text),
+		}
+	}
+}
+
+/// Reduces normalized words to a common stem, so that e.g. "installing" and
+/// "installs" match each other. Chosen when the index is built and applied to
+/// both keywords and queries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Stemmer {
+	/// Keep words as they are
+	#[default]
+	None,
+	/// The Porter stemmer for English. Queries do not say which language they
+	/// are in, so it also applies to documents in other languages, where it only
+	/// strips English suffixes.
+	Porter,
+}
+
+impl Stemmer {
+	/// Stem each word of a normalized, space-separated keyword or phrase.
+	#[cfg(any(feature = "cli", feature = "wasm", test))]
+	pub fn stem<'a>(&self, words: &'a str) -> Cow<'a, str> {
+		match self {
+			Stemmer::None => Cow::Borrowed(words),
+			Stemmer::Porter => Cow::Owned(
+				words
+					.split(' ')
+					.map(stem::porter)
+					.collect::<Vec<String>>()
+					.join(" "),
+			),
+		}
+	}
+}
+
+/// A document that a keyword was extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Posting {
+	document: usize,
+	/// Keyword score, quantized with the index's `score_scale`
+	score: u16,
+	/// Bit set of the [`Field`]s the keyword was found in
+	fields: u8,
+	/// Field that `score` was taken from, the first one the keyword was extracted
+	/// from
+	origin: Field,
+	/// Number of occurrences of the keyword in the document body
+	term_frequency: u16,
+}
+
+impl Posting {
+	/// Serialized size: document (little-endian u32), score (little-endian u16),
+	/// fields, origin and term frequency (little-endian u16)
+	const SIZE: usize = 10;
+
+	#[cfg(any(feature = "cli", test))]
+	fn write(&self, out: &mut Vec<u8>) {
+		out.extend_from_slice(&(self.document as u32).to_le_bytes());
+		out.extend_from_slice(&self.score.to_le_bytes());
+		out.push(self.fields);
+		out.push(self.origin as u8);
+		out.extend_from_slice(&self.term_frequency.to_le_bytes());
+	}
+
+	#[cfg(any(feature = "wasm", test))]
+	fn read(bytes: &[u8]) -> Self {
+		Posting {
+			document: read_u32(bytes, 0) as usize,
+			score: u16::from_le_bytes([bytes[4], bytes[5]]),
+			fields: bytes[6],
+			// checked by `IndexView::validate`
+			origin: Field::ALL[bytes[7] as usize],
+			term_frequency: u16::from_le_bytes([bytes[8], bytes[9]]),
+		}
 	}
 }
 
+/// Serialize posting lists into per-keyword offsets (little-endian u32, counted in
+/// postings) and the concatenated postings.
 #[cfg(any(feature = "cli", test))]
-pub fn build_index(documents: Vec<Document>) -> Result<Index, Box<dyn std::error::Error>> {
-	use std::collections::HashSet;
+fn encode_postings(lists: &[Vec<Posting>]) -> (Vec<u8>, Vec<u8>) {
+	let mut offsets = Vec::with_capacity(lists.len() * 4);
+	let mut postings = Vec::new();
+	for list in lists {
+		offsets.extend_from_slice(&((postings.len() / Posting::SIZE) as u32).to_le_bytes());
+		for posting in list {
+			posting.write(&mut postings);
+		}
+	}
+	(offsets, postings)
+}
 
-	let stop_words = include_str!("../english.stop")
-		.lines()
-		.filter(|line| !line.is_empty() && !line.starts_with('#'))
-		.map(|line| line.to_lowercase())
-		.collect::<HashSet<String>>();
+/// How an index keyword was matched against a query word.
+///
+/// Variants are ordered from strongest to weakest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchType {
+	/// The keyword is identical to the query word
+	Exact,
+	/// The keyword starts with the query word
+	Prefix,
+	/// The keyword is within the Levenshtein distance of the query word
+	Fuzzy,
+}
 
-	let sw = rake::StopWords::from(stop_words);
-	let rake = rake::Rake::new(sw.clone());
+/// An index keyword that contributed to a search hit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeywordMatch {
+	pub keyword: String,
+	pub match_type: MatchType,
+	/// Number of occurrences of the keyword in the document body
+	pub term_frequency: u16,
+}
 
-	let mut strings: Vec<&str> = Vec::new();
-	let mut keywords_to_documents: HashMap<String, Vec<(&Document, f64)>> = HashMap::new();
-	let mut doc_index_map: HashMap<&str, usize> = HashMap::new();
+/// A short excerpt of a document body around its matches.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snippet {
+	pub text: String,
+	/// Ranges of the matched words in `text`, in UTF-16 code units so that they
+	/// can be passed to `String.prototype.slice` in JavaScript. Empty when the
+	/// words are marked with `<mark>` tags instead.
+	pub highlights: Vec<std::ops::Range<usize>>,
+}
 
-	for (doc_index, doc) in documents.iter().enumerate() {
-		doc_index_map.insert(&doc.href, doc_index);
-		strings.push(&doc.title);
-		strings.push(&doc.category);
-		strings.push(&doc.href);
-		strings.push(&doc.body);
+/// How to build the [`Snippet`] of each search hit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SnippetOptions {
+	/// Approximate length of the snippet in characters
+	pub length: usize,
+	/// Wrap matched words in `<mark>` tags, HTML-escaping the rest of the text
+	pub mark: bool,
+}
 
-		let mut keyword_set: HashSet<String> = HashSet::new();
-		let mut keywords: Vec<(String, f64)> = Vec::new();
+impl Default for SnippetOptions {
+	fn default() -> Self {
+		Self {
+			length: 160,
+			mark: false,
+		}
+	}
+}
 
-		// Add explicit keywords from document metadata
-		if let Some(kw) = &doc.keywords {
-			for k in kw {
-				let keyword = k
-					.trim_matches(|c: char| !c.is_alphanumeric())
-					.to_lowercase();
-				if !keyword.is_empty() && !sw.contains(&keyword.clone()) && !keyword_set.contains(&keyword)
-				{
-					keywords.push((keyword.clone(), 100.0));
-					keyword_set.insert(keyword.clone());
-				}
-			}
+/// How many typos a query word may contain, by its length in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TypoTolerance {
+	/// Shortest word that may contain one typo
+	pub one_typo: usize,
+	/// Shortest word that may contain two typos
+	pub two_typos: usize,
+	/// Fraction of a keyword's score lost per typo
+	pub typo_penalty: f32,
+}
+
+impl Default for TypoTolerance {
+	fn default() -> Self {
+		Self {
+			one_typo: 4,
+			two_typos: 8,
+			typo_penalty: 0.25,
 		}
+	}
+}
 
-		// add keywords from title
-		let title_keywords = doc
-			.title
-			.split_whitespace()
-			.map(|w| {
-				w.trim_matches(|c: char| !c.is_alphanumeric())
-					.to_lowercase()
-			})
-			.filter(|w| !w.is_empty() && !sw.contains(&w.clone()))
-			.collect::<HashSet<String>>(); // deduplicate
+impl TypoTolerance {
+	/// Number of typos allowed in `word`.
+	pub fn typos(&self, word: &str) -> u32 {
+		let length = word.chars().count();
+		if length >= self.two_typos {
+			2
+		} else if length >= self.one_typo {
+			1
+		} else {
+			0
+		}
+	}
 
-		for tk in title_keywords {
-			if !keyword_set.contains(&tk) {
-				keywords.push((tk.clone(), 90.0));
-				keyword_set.insert(tk.clone());
+	/// Weight of a keyword score after `typos` typos.
+	#[cfg(any(feature = "wasm", test))]
+	fn weight(&self, typos: u32) -> f32 {
+		(1.0 - self.typo_penalty * typos as f32).max(0.0)
+	}
+}
+
+/// What each search hit carries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResultMode {
+	/// Decode the stored fields of every hit
+	#[default]
+	Documents,
+	/// Only return document ids, to be decoded later with [`get_document`]
+	Ids,
+}
+
+/// Orders search hits by a sortable field, see [`SearchOptions::sort`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SortKey {
+	/// `title` or a sortable custom field, see [`FieldSchema::sortable`]
+	pub field: String,
+	/// Highest values first. Documents without a value come last either way.
+	pub descending: bool,
+}
+
+/// A document returned by [`search`], along with why and how well it matched.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+	/// Document id, for use with [`get_document`]
+	pub id: usize,
+	/// The matching document, unless only ids were requested. Its body is left
+	/// empty when a snippet is requested.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub document: Option<Document>,
+	/// Aggregated score of all matched keywords
+	pub score: f32,
+	/// Matched index keywords, strongest match first
+	pub matches: Vec<KeywordMatch>,
+	/// Body excerpt, if requested through [`SearchOptions::snippet`]
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub snippet: Option<Snippet>,
+}
+
+/// Options for [`search_with_options`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchOptions {
+	/// Maximum number of hits to return
+	pub max_results: usize,
+	/// Number of ranked hits to skip, for pagination
+	pub offset: usize,
+	/// Only return documents in one of these categories
+	pub categories: Option<Vec<String>>,
+	/// Only return documents whose filterable custom fields have one of the given
+	/// values, e.g. `{ "product": ["cli", "sdk"] }`
+	pub filters: BTreeMap<String, Vec<String>>,
+	/// Only return documents matching a filter expression over the filterable
+	/// custom fields, e.g. `version >= 3.2 AND updated > 2024-01-01`, see
+	/// [`filter::parse`]
+	pub filter: Option<String>,
+	/// Order hits by these fields before relevance, e.g. the newest first. Hits
+	/// with equal values are ranked by relevance.
+	pub sort: Vec<SortKey>,
+	/// How much document popularity raises scores: the most popular documents
+	/// score `1 + popularity_weight` times as much as the least popular ones
+	pub popularity_weight: f32,
+	/// Multiply the score of keywords by the field they were taken from, e.g.
+	/// `{ "title": 3.0, "body": 0.5 }` to favour titles in a typeahead. Fields
+	/// left out keep their score. Keywords matched only in other fields, such as
+	/// `category:api`, take the boost of those fields instead.
+	///
+	/// Unlike [`Bm25Parameters::boosts`], which is fixed when the index is built
+	/// and lists every field, these boosts are chosen per search, apply to both
+	/// rankings and only name the fields to change, so their default is 1.
+	pub field_boosts: BTreeMap<Field, f32>,
+	/// Count the matching documents per category
+	pub facets: bool,
+	/// Return a snippet of each body instead of the full body
+	pub snippet: Option<SnippetOptions>,
+	/// Whether to decode the documents of the hits or only return their ids
+	pub mode: ResultMode,
+	/// Fuzzy matching of query words, `None` to only match exactly or by prefix
+	pub typo_tolerance: Option<TypoTolerance>,
+	/// Fraction of a keyword's score lost when it does not equal the query word,
+	/// i.e. for prefix and fuzzy matches. Fuzzy matches also lose the typo penalty,
+	/// so exact matches rank above prefix matches of keywords scoring as much, and
+	/// those above fuzzy ones. Raise it to favour exact matches over keywords
+	/// from stronger fields.
+	pub prefix_penalty: f32,
+}
+
+impl Default for SearchOptions {
+	fn default() -> Self {
+		Self {
+			max_results: 10,
+			offset: 0,
+			categories: None,
+			filters: BTreeMap::new(),
+			filter: None,
+			sort: Vec::new(),
+			popularity_weight: 0.5,
+			field_boosts: BTreeMap::new(),
+			facets: false,
+			snippet: None,
+			mode: ResultMode::Documents,
+			typo_tolerance: Some(TypoTolerance::default()),
+			prefix_penalty: 0.2,
+		}
+	}
+}
+
+impl SearchOptions {
+	/// Weight of a keyword score, by how the keyword matched.
+	#[cfg(any(feature = "wasm", test))]
+	fn match_weight(&self, match_type: MatchType, typos: u32) -> f32 {
+		let inexact = 1.0 - self.prefix_penalty;
+		match match_type {
+			MatchType::Exact => 1.0,
+			MatchType::Prefix => inexact,
+			MatchType::Fuzzy => {
+				inexact
+					* self
+						.typo_tolerance
+						.as_ref()
+						.map_or(1.0, |tolerance| tolerance.weight(typos))
 			}
 		}
+		.max(0.0)
+	}
+}
 
-		let body_keywords = rake.run_fragments(vec![doc.body.as_str()]);
-		let mut single_word_budget = 5;
-		let mut double_word_budget = 3;
+/// Number of matching documents in a category.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryFacet {
+	pub category: String,
+	pub count: usize,
+}
 
-		for k in &body_keywords {
-			let keyword = k.keyword.to_lowercase();
+/// Results of [`search_with_options`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+	pub hits: Vec<SearchHit>,
+	/// Number of matching documents across all pages
+	pub total: usize,
+	/// Matching documents per category, most frequent first. Counted before the
+	/// category filter is applied, and only if [`SearchOptions::facets`] is set.
+	pub facets: Vec<CategoryFacet>,
+}
 
-			// continue if keyword is already in title keywords
-			if keyword_set.contains(&keyword) {
-				continue;
-			}
+/// Scores of the keywords extracted from each source, see [`IndexOptions`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct KeywordWeights {
+	/// Score of the explicit keywords of a document
+	pub keywords: f64,
+	/// Score of title words
+	pub title: f64,
+	/// Multiplier of the RAKE score of body keywords
+	pub body: f64,
+	/// Score of category words
+	pub category: f64,
+	/// Score of href words
+	pub href: f64,
+	/// Score of code identifiers in the body and of their parts, e.g.
+	/// `max_retry_count`, `max`, `retry` and `count`. Identifiers in the title
+	/// score like other title words.
+	pub identifiers: f64,
+}
 
-			let whitespace_count = k.keyword.matches(' ').count();
+impl Default for KeywordWeights {
+	fn default() -> Self {
+		Self {
+			keywords: 100.0,
+			title: 90.0,
+			body: 1.0,
+			category: 80.0,
+			href: 70.0,
+			identifiers: 5.0,
+		}
+	}
+}
 
-			if whitespace_count == 0 && single_word_budget > 0 {
-				single_word_budget -= 1;
-			} else if whitespace_count == 1 && double_word_budget > 0 {
-				double_word_budget -= 1;
-			} else {
-				continue;
-			}
+/// How search hits are scored, chosen when the index is built.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Ranking {
+	/// Sum of the stored keyword scores, see [`KeywordWeights`]
+	#[default]
+	Keyword,
+	/// BM25F over the matched fields, see [`Bm25Parameters`]
+	Bm25,
+}
 
-			keywords.push((keyword.clone(), k.score));
-			keyword_set.insert(keyword.clone());
+/// Per-field weights of a keyword occurrence.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FieldBoosts {
+	pub keywords: f32,
+	pub title: f32,
+	pub category: f32,
+	pub href: f32,
+	pub body: f32,
+	pub custom: f32,
+}
 
-			if single_word_budget == 0 && double_word_budget == 0 {
-				break;
-			}
+impl Default for FieldBoosts {
+	fn default() -> Self {
+		Self {
+			keywords: 3.0,
+			title: 2.0,
+			category: 1.0,
+			href: 1.0,
+			body: 1.0,
+			custom: 1.0,
 		}
+	}
+}
 
-		for k in keywords.iter() {
-			keywords_to_documents
-				.entry(k.0.clone())
-				.or_default()
-				.push((doc, k.1));
+impl FieldBoosts {
+	pub fn boost(&self, field: Field) -> f32 {
+		match field {
+			Field::Keywords => self.keywords,
+			Field::Title => self.title,
+			Field::Category => self.category,
+			Field::Href => self.href,
+			Field::Body => self.body,
+			Field::Custom => self.custom,
 		}
 	}
+}
 
-	println!("Extracted {} unique keywords", keywords_to_documents.len());
+/// Parameters of [`Ranking::Bm25`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Bm25Parameters {
+	/// Term frequency saturation
+	pub k1: f32,
+	/// Document length normalization, from 0 (none) to 1 (full)
+	pub b: f32,
+	/// Weight of an occurrence in each field. Body occurrences count their term
+	/// frequency, other fields count once. These are stored in the index, the
+	/// query-time [`SearchOptions::field_boosts`] multiply them.
+	pub boosts: FieldBoosts,
+}
 
-	let mut fst_builder = fst::MapBuilder::memory();
-	let mut keyword_to_documents: Vec<Vec<(usize, u8)>> = Vec::new();
-	let mut keywords: Vec<String> = keywords_to_documents.keys().cloned().collect();
-	keywords.sort();
+impl Default for Bm25Parameters {
+	fn default() -> Self {
+		Self {
+			k1: 1.2,
+			b: 0.75,
+			boosts: FieldBoosts::default(),
+		}
+	}
+}
 
-	for (index, keyword) in keywords.iter().enumerate() {
-		fst_builder.insert(keyword, index as u64)?;
+/// Options for [`build_index_with_options`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IndexOptions {
+	/// Keyword scores per source
+	pub weights: KeywordWeights,
+	/// Number of RAKE body keywords kept per document, by phrase length: the first
+	/// entry limits single words, the second two-word phrases and so on. Longer
+	/// phrases share the last entry.
+	pub body_budgets: Vec<usize>,
+	/// Longest RAKE body phrase to index, in words
+	pub max_phrase_length: usize,
+	/// Also index every body word, not only the RAKE keywords. Body words that
+	/// RAKE did not pick are scored by their term frequency times the body weight.
+	pub full_text: bool,
+	/// How search hits are scored
+	pub ranking: Ranking,
+	/// Parameters used when `ranking` is [`Ranking::Bm25`]
+	pub bm25: Bm25Parameters,
+	/// Stemmer applied to keywords, and to queries at search time
+	pub stemmer: Stemmer,
+	/// Language of documents that do not set one, see [`STOP_WORDS`]
+	pub language: String,
+	/// Additional stop words, used for every language
+	pub stop_words: Vec<String>,
+	/// Groups of words or phrases with the same meaning, e.g. `["k8s",
+	/// "kubernetes"]`. Stored in the index, a query for one of them also finds
+	/// the others.
+	pub synonyms: Vec<Vec<String>>,
+	/// How the custom fields of documents are indexed. Fields without a schema
+	/// are ignored.
+	pub fields: Vec<FieldSchema>,
+}
 
-		let mut doc_scores = keywords_to_documents.get(keyword).unwrap().clone();
-		doc_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+impl Default for IndexOptions {
+	fn default() -> Self {
+		Self {
+			weights: KeywordWeights::default(),
+			body_budgets: vec![5, 3],
+			max_phrase_length: 2,
+			full_text: false,
+			ranking: Ranking::Keyword,
+			bm25: Bm25Parameters::default(),
+			stemmer: Stemmer::None,
+			language: "en".to_string(),
+			stop_words: Vec::new(),
+			synonyms: Vec::new(),
+			fields: Vec::new(),
+		}
+	}
+}
 
-		let entry = doc_scores
-			.iter()
-			.map(|(doc, score)| (doc_index_map[doc.href.as_str()], *score as u8))
-			.collect::<Vec<(usize, u8)>>();
+/// How the values of a custom field are compared in filters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FieldKind {
+	/// Compared as a whole for equality
+	#[default]
+	Text,
+	/// A decimal number such as `3.2`
+	Number,
+	/// A date such as `2024-05-31`, optionally followed by a time that is ignored
+	Date,
+	/// A dotted version such as `3.10`, compared part by part so that `3.10`
+	/// follows `3.9`
+	Version,
+}
 
-		keyword_to_documents.push(entry);
+impl FieldKind {
+	/// Parse a value into the number it is compared by, dates as days since
+	/// 1970-01-01. Text values are not numbers and versions are compared by
+	/// [`FieldKind::version`] instead.
+	#[cfg(any(feature = "cli", feature = "wasm", test))]
+	fn parse(self, value: &str) -> Option<f64> {
+		match self {
+			FieldKind::Text | FieldKind::Version => None,
+			FieldKind::Number => value
+				.trim()
+				.parse::<f64>()
+				.ok()
+				.filter(|number| number.is_finite()),
+			FieldKind::Date => {
+				let date = value.trim().split(['T', ' ']).next()?;
+				let mut parts = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+				let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+				let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+				let month_length = match month {
+					2 if leap => 29,
+					2 => 28,
+					4 | 6 | 9 | 11 => 30,
+					_ => 31,
+				};
+				if !(1..=12).contains(&month) || !(1..=month_length).contains(&day) {
+					return None;
+				}
+				// days from the civil calendar, see https://howardhinnant.github.io/date_algorithms.html
+				let year = if month <= 2 { year - 1 } else { year };
+				let era = year.div_euclid(400);
+				let year_of_era = year - era * 400;
+				let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+				let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+				Some((era * 146097 + day_of_era - 719468) as f64)
+			}
+		}
 	}
 
-	let fst = fst_builder.into_inner().unwrap();
-	let document_strings = FsstStrVec::from_strings(&strings);
+	/// Parse a dotted version into its numeric parts, without trailing zeros so
+	/// that `3.2.0` equals `3.2`.
+	#[cfg(any(feature = "cli", feature = "wasm", test))]
+	fn version(value: &str) -> Option<Vec<u64>> {
+		let mut parts = value
+			.trim()
+			.split('.')
+			.map(|part| part.parse::<u64>().ok())
+			.collect::<Option<Vec<u64>>>()?;
+		while parts.len() > 1 && parts.last() == Some(&0) {
+			parts.pop();
+		}
+		Some(parts)
+	}
+}
 
-	Ok(Index {
-		fst,
-		document_strings,
-		keyword_to_documents,
-	})
+/// How a custom document field is indexed, see [`Document::fields`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FieldSchema {
+	pub name: String,
+	/// Index the words of the field as keywords, matched like title and body words
+	pub searchable: bool,
+	/// Return the field with search results
+	pub stored: bool,
+	/// Allow restricting results to given values, see [`SearchOptions::filters`]
+	/// and [`SearchOptions::filter`]
+	pub filterable: bool,
+	/// Allow sorting results by the field, see [`SearchOptions::sort`]
+	pub sortable: bool,
+	/// Type of the values of a filterable or sortable field. Filterable number,
+	/// date and version fields are stored as columns of numbers that can be
+	/// compared with `<` and `>`, sortable ones are ordered by value rather than
+	/// as text.
+	pub kind: FieldKind,
+	/// Score of the keywords of a searchable field
+	pub weight: f64,
 }
 
-#[cfg(any(feature = "wasm", test))]
-pub fn search(
-	index: &Index,
-	query: &str,
-	max_results: usize,
-) -> Result<Vec<Document>, Box<dyn std::error::Error>> {
-	use fst::automaton::Levenshtein;
-	use fst::map::OpBuilder;
-	use fst::{Automaton, Streamer};
-	use std::collections::HashSet;
+impl Default for FieldSchema {
+	fn default() -> Self {
+		Self {
+			name: String::new(),
+			searchable: false,
+			stored: true,
+			filterable: false,
+			sortable: false,
+			kind: FieldKind::Text,
+			weight: 50.0,
+		}
+	}
+}
 
-	let map = fst::Map::new(&index.fst)?;
+/// Bundled stop word lists, by ISO 639-1 language code.
+#[cfg(any(feature = "cli", test))]
+pub const STOP_WORDS: &[(&str, &str)] = &[
+	("en", include_str!("../english.stop")),
+	("de", include_str!("../german.stop")),
+	("fr", include_str!("../french.stop")),
+	("es", include_str!("../spanish.stop")),
+	("ja", include_str!("../japanese.stop")),
+];
+
+/// Parse a stop word list with one word per line and `#` comments.
+#[cfg(any(feature = "cli", test))]
+pub fn parse_stop_words(list: &str) -> impl Iterator<Item = String> + '_ {
+	list
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(str::to_lowercase)
+}
 
-	let mut query_words: HashSet<String> = query
-		.split_whitespace()
-		.map(|w| {
-			w.trim_matches(|c: char| !c.is_alphanumeric())
-				.to_lowercase()
+/// Parse a synonym list with one group of comma-separated synonyms per line,
+/// e.g. `login, sign in`, and `#` comments.
+#[cfg(any(feature = "cli", test))]
+pub fn parse_synonyms(list: &str) -> impl Iterator<Item = Vec<String>> + '_ {
+	list
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(|line| {
+			line
+				.split(',')
+				.map(str::trim)
+				.filter(|synonym| !synonym.is_empty())
+				.map(str::to_string)
+				.collect()
 		})
-		.filter(|w| !w.is_empty())
-		.collect();
+}
 
-	query_words.insert(query.to_lowercase());
+/// A search index, as built by [`build_index`].
+///
+/// Its serialized form is laid out so that it can be searched in place through
+/// an [`IndexView`], without deserializing it first. It is written with
+/// [`Index::to_bytes`] and checked when read back with [`IndexView::from_bytes`]
+/// or [`Index::from_bytes`].
+#[derive(Debug)]
+pub struct Index {
+	/// FST vector for keyword to entry index
+	fst: Vec<u8>,
 
-	let mut keywords: Vec<(String, u64)> = Vec::new();
+	/// FSST string vector of the title, href and body of each document
+	document_strings: FsstStrVec,
 
-	for query_word in query_words {
-		use fst::automaton::Str;
+	/// Sorted, deduplicated document categories
+	categories: Vec<String>,
 
-		let lev = Levenshtein::new(query_word.as_str(), 1)?;
-		let prefix = Str::new(query_word.as_str()).starts_with();
+	/// Position in `categories` of each document's category, as little-endian u32
+	document_categories: Vec<u8>,
 
-		let mut op = OpBuilder::new()
-			.add(map.search(lev))
-			.add(map.search(prefix))
-			.union();
+	/// Number of body words of each document, as little-endian u32
+	document_lengths: Vec<u8>,
 
-		while let Some((keyword, indexed_value)) = op.next() {
-			let keyword_str = String::from_utf8(keyword.to_vec())?;
-			let score = indexed_value.to_vec().get(0).unwrap().value;
-			keywords.push((keyword_str, score));
-		}
-	}
+	/// Start of each keyword's postings in `postings`, as little-endian u32
+	posting_offsets: Vec<u8>,
 
-	// Sort keywords by length (shorter first)
-	keywords.sort_by_key(|(kw, _)| kw.len());
+	/// Concatenated keyword to document postings, see [`Posting::SIZE`]
+	postings: Vec<u8>,
 
-	let mut documents: HashMap<usize, u8> = HashMap::new();
+	/// How search hits are scored
+	ranking: Ranking,
+	bm25: Bm25Parameters,
 
-	for (_, keyword_index) in keywords {
-		let documents_matching_keyword = &index.keyword_to_documents[keyword_index as usize];
+	/// Mean of the document lengths, for BM25 length normalization
+	average_document_length: f32,
 
-		for (document_index, score) in documents_matching_keyword {
-			let entry = documents.entry(*document_index).or_insert(0);
-			*entry = entry.saturating_add(*score);
-		}
-	}
+	/// Power of two that keyword scores are multiplied by before being stored as
+	/// u16, chosen so that the highest score uses most of the range
+	score_scale: f32,
 
-	// sort documents by score (descending), then by document index (ascending) for stable ordering
-	let mut documents: Vec<(usize, u8)> = documents.into_iter().collect();
-	documents.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
-	documents.truncate(max_results);
+	/// Stemmer the keywords were reduced with
+	stemmer: Stemmer,
 
-	let mut result: Vec<Document> = Vec::new();
+	/// Groups of synonyms, normalized and stemmed like keywords
+	synonyms: Vec<Vec<String>>,
 
-	for (document_index, _score) in documents {
-		let title = index
-			.document_strings
-			.get(document_index * 4)
-			.ok_or_else(|| "Failed to get document title")?;
-		let category = index
-			.document_strings
-			.get(document_index * 4 + 1)
-			.ok_or_else(|| "Failed to get document category")?;
-		let href = index
-			.document_strings
-			.get(document_index * 4 + 2)
-			.ok_or_else(|| "Failed to get document href")?;
-		let body = index
-			.document_strings
-			.get(document_index * 4 + 3)
-			.ok_or_else(|| "Failed to get document body")?;
+	/// Names of the stored custom fields, whose values follow the title, href and
+	/// body of each document in `document_strings`
+	stored_fields: Vec<String>,
 
-		let document = Document {
-			title,
-			category,
-			href,
-			body,
-			keywords: None,
-		};
+	/// Values of the filterable text custom fields
+	filter_fields: Vec<FilterField>,
 
-		result.push(document);
+	/// Values of the filterable number and date custom fields
+	attributes: Vec<Attribute>,
+
+	/// Sort order of the title and the sortable custom fields
+	sort_columns: Vec<SortColumn>,
+
+	/// Popularity of each document relative to the most popular one, from 0 to
+	/// 255 on a logarithmic scale. Empty when no document has a popularity.
+	popularity: Vec<u8>,
+}
+
+/// The values of a filterable custom field, stored like categories.
+#[derive(Debug)]
+struct FilterField {
+	name: String,
+	/// Sorted, deduplicated values
+	values: Vec<String>,
+	/// Position in `values` of each document's value as little-endian u32,
+	/// `u32::MAX` when a document has none
+	documents: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FilterFieldView<'a> {
+	name: &'a str,
+	#[serde(borrow)]
+	values: Vec<&'a str>,
+	documents: &'a [u8],
+}
+
+/// The values of a number, date or version custom field, see [`FieldKind::parse`].
+#[derive(Debug)]
+struct Attribute {
+	name: String,
+	kind: FieldKind,
+	/// Distinct versions in ascending order, empty for other kinds
+	versions: Vec<String>,
+	/// Value of each document as little-endian f64, or the position of its
+	/// version, NaN when a document has none
+	values: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AttributeView<'a> {
+	name: &'a str,
+	kind: FieldKind,
+	#[serde(borrow)]
+	versions: Vec<&'a str>,
+	values: &'a [u8],
+}
+
+/// The order of the documents by a field, so that sorting never decodes values.
+#[derive(Debug)]
+struct SortColumn {
+	name: String,
+	/// Rank of each document's value as little-endian u32, where equal values
+	/// share a rank and `u32::MAX` means no value
+	ranks: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SortColumnView<'a> {
+	name: &'a str,
+	ranks: &'a [u8],
+}
+
+impl SortColumn {
+	/// Rank documents by the keys of their values, `None` for documents without one.
+	#[cfg(any(feature = "cli", test))]
+	fn new<K: PartialOrd>(name: &str, keys: Vec<Option<K>>) -> SortColumn {
+		let mut ranks = vec![u32::MAX; keys.len()];
+		let mut order: Vec<(usize, K)> = keys
+			.into_iter()
+			.enumerate()
+			.filter_map(|(document, key)| Some((document, key?)))
+			.collect();
+		order.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+		let mut rank = 0;
+		let mut previous: Option<&K> = None;
+		for (document, key) in &order {
+			if previous.is_some_and(|previous| previous != key) {
+				rank += 1;
+			}
+			ranks[*document] = rank;
+			previous = Some(key);
+		}
+
+		SortColumn {
+			name: name.to_string(),
+			ranks: ranks.iter().flat_map(|rank| rank.to_le_bytes()).collect(),
+		}
+	}
+}
+
+impl Index {
+	/// Load an owned copy of a serialized index. Prefer [`IndexView::from_bytes`]
+	/// to search the bytes in place.
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+		let view = IndexView::from_bytes(bytes)?;
+		Ok(Index {
+			fst: view.fst.to_vec(),
+			document_strings: FsstStrVec {
+				dict_syms: view.document_strings.dict_syms.to_vec(),
+				dict_lens: view.document_strings.dict_lens.to_vec(),
+				offsets: view.document_strings.offsets.to_vec(),
+				data: view.document_strings.data.to_vec(),
+				symbols: OnceLock::new(),
+			},
+			categories: view.categories.iter().map(|c| c.to_string()).collect(),
+			document_categories: view.document_categories.to_vec(),
+			document_lengths: view.document_lengths.to_vec(),
+			posting_offsets: view.posting_offsets.to_vec(),
+			postings: view.postings.to_vec(),
+			ranking: view.ranking,
+			bm25: view.bm25,
+			average_document_length: view.average_document_length,
+			score_scale: view.score_scale,
+			stemmer: view.stemmer,
+			synonyms: view
+				.synonyms
+				.iter()
+				.map(|group| group.iter().map(|s| s.to_string()).collect())
+				.collect(),
+			stored_fields: view.stored_fields.iter().map(|f| f.to_string()).collect(),
+			filter_fields: view
+				.filter_fields
+				.iter()
+				.map(|field| FilterField {
+					name: field.name.to_string(),
+					values: field.values.iter().map(|v| v.to_string()).collect(),
+					documents: field.documents.to_vec(),
+				})
+				.collect(),
+			attributes: view
+				.attributes
+				.iter()
+				.map(|attribute| Attribute {
+					name: attribute.name.to_string(),
+					kind: attribute.kind,
+					versions: attribute.versions.iter().map(|v| v.to_string()).collect(),
+					values: attribute.values.to_vec(),
+				})
+				.collect(),
+			sort_columns: view
+				.sort_columns
+				.iter()
+				.map(|column| SortColumn {
+					name: column.name.to_string(),
+					ranks: column.ranks.to_vec(),
+				})
+				.collect(),
+			popularity: view.popularity.to_vec(),
+		})
+	}
+
+	pub fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+		Ok(postcard::to_allocvec(&self.view())?)
+	}
+
+	/// Borrow the index for searching.
+	pub fn view(&self) -> IndexView<'_> {
+		IndexView {
+			fst: &self.fst,
+			document_strings: self.document_strings.view(),
+			categories: self.categories.iter().map(String::as_str).collect(),
+			document_categories: &self.document_categories,
+			document_lengths: &self.document_lengths,
+			posting_offsets: &self.posting_offsets,
+			postings: &self.postings,
+			ranking: self.ranking,
+			bm25: self.bm25,
+			average_document_length: self.average_document_length,
+			score_scale: self.score_scale,
+			stemmer: self.stemmer,
+			synonyms: self
+				.synonyms
+				.iter()
+				.map(|group| group.iter().map(String::as_str).collect())
+				.collect(),
+			stored_fields: self.stored_fields.iter().map(String::as_str).collect(),
+			filter_fields: self
+				.filter_fields
+				.iter()
+				.map(|field| FilterFieldView {
+					name: &field.name,
+					values: field.values.iter().map(String::as_str).collect(),
+					documents: &field.documents,
+				})
+				.collect(),
+			attributes: self
+				.attributes
+				.iter()
+				.map(|attribute| AttributeView {
+					name: &attribute.name,
+					kind: attribute.kind,
+					versions: attribute.versions.iter().map(String::as_str).collect(),
+					values: &attribute.values,
+				})
+				.collect(),
+			sort_columns: self
+				.sort_columns
+				.iter()
+				.map(|column| SortColumnView {
+					name: &column.name,
+					ranks: &column.ranks,
+				})
+				.collect(),
+			popularity: &self.popularity,
+		}
+	}
+}
+
+/// A search index read in place from its serialized bytes.
+///
+/// The FST, document strings, categories and postings all borrow from the
+/// bytes, so loading a view only decodes the FSST symbol table.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexView<'a> {
+	fst: &'a [u8],
+	document_strings: FsstStrView<'a>,
+	categories: Vec<&'a str>,
+	document_categories: &'a [u8],
+	document_lengths: &'a [u8],
+	posting_offsets: &'a [u8],
+	postings: &'a [u8],
+	ranking: Ranking,
+	bm25: Bm25Parameters,
+	average_document_length: f32,
+	score_scale: f32,
+	stemmer: Stemmer,
+	synonyms: Vec<Vec<&'a str>>,
+	stored_fields: Vec<&'a str>,
+	filter_fields: Vec<FilterFieldView<'a>>,
+	attributes: Vec<AttributeView<'a>>,
+	sort_columns: Vec<SortColumnView<'a>>,
+	popularity: &'a [u8],
+}
+
+/// The serialized form of an [`IndexView`], which only becomes a view once
+/// [`IndexView::from_bytes`] has checked it.
+#[derive(Deserialize)]
+struct IndexWire<'a> {
+	fst: &'a [u8],
+	#[serde(borrow)]
+	document_strings: FsstStrView<'a>,
+	#[serde(borrow)]
+	categories: Vec<&'a str>,
+	document_categories: &'a [u8],
+	document_lengths: &'a [u8],
+	posting_offsets: &'a [u8],
+	postings: &'a [u8],
+	ranking: Ranking,
+	bm25: Bm25Parameters,
+	average_document_length: f32,
+	score_scale: f32,
+	stemmer: Stemmer,
+	#[serde(borrow)]
+	synonyms: Vec<Vec<&'a str>>,
+	#[serde(borrow)]
+	stored_fields: Vec<&'a str>,
+	#[serde(borrow)]
+	filter_fields: Vec<FilterFieldView<'a>>,
+	#[serde(borrow)]
+	attributes: Vec<AttributeView<'a>>,
+	#[serde(borrow)]
+	sort_columns: Vec<SortColumnView<'a>>,
+	popularity: &'a [u8],
+}
+
+impl<'a> IndexView<'a> {
+	/// Read an index in place, checking that everything it refers to is in range
+	/// so that searching it cannot panic.
+	pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Box<dyn std::error::Error>> {
+		let wire: IndexWire = postcard::from_bytes(bytes)?;
+		let view = IndexView {
+			fst: wire.fst,
+			document_strings: wire.document_strings,
+			categories: wire.categories,
+			document_categories: wire.document_categories,
+			document_lengths: wire.document_lengths,
+			posting_offsets: wire.posting_offsets,
+			postings: wire.postings,
+			ranking: wire.ranking,
+			bm25: wire.bm25,
+			average_document_length: wire.average_document_length,
+			score_scale: wire.score_scale,
+			stemmer: wire.stemmer,
+			synonyms: wire.synonyms,
+			stored_fields: wire.stored_fields,
+			filter_fields: wire.filter_fields,
+			attributes: wire.attributes,
+			sort_columns: wire.sort_columns,
+			popularity: wire.popularity,
+		};
+		view.validate()?;
+		Ok(view)
+	}
+
+	fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+		use fst::Streamer;
+
+		if !self.document_categories.len().is_multiple_of(4)
+			|| self.document_lengths.len() != self.document_categories.len()
+			|| !self.posting_offsets.len().is_multiple_of(4)
+			|| !self.postings.len().is_multiple_of(Posting::SIZE)
+			|| self.document_strings.len() != self.document_count() * self.strings_per_document()
+			|| self
+				.filter_fields
+				.iter()
+				.any(|field| field.documents.len() != self.document_categories.len())
+			|| self
+				.attributes
+				.iter()
+				.any(|attribute| attribute.values.len() != self.document_count() * 8)
+			|| self
+				.sort_columns
+				.iter()
+				.any(|column| column.ranks.len() != self.document_categories.len())
+			|| !(self.popularity.is_empty() || self.popularity.len() == self.document_count())
+		{
+			return Err("Malformed index: column lengths differ".into());
+		}
+
+		let words = |bytes: &'a [u8]| (0..bytes.len() / 4).map(move |i| read_u32(bytes, i) as usize);
+		if !words(self.document_categories).all(|category| category < self.categories.len()) {
+			return Err("Malformed index: category out of range".into());
+		}
+		if !self.filter_fields.iter().all(|field| {
+			words(field.documents).all(|value| value == u32::MAX as usize || value < field.values.len())
+		}) {
+			return Err("Malformed index: filter value out of range".into());
+		}
+
+		let posting_count = self.postings.len() / Posting::SIZE;
+		if !words(self.posting_offsets).is_sorted()
+			|| !words(self.posting_offsets).all(|offset| offset <= posting_count)
+		{
+			return Err("Malformed index: posting offset out of range".into());
+		}
+		let document_count = self.document_count();
+		if !self
+			.postings
+			.chunks_exact(Posting::SIZE)
+			.all(|posting| (read_u32(posting, 0) as usize) < document_count)
+		{
+			return Err("Malformed index: posting document out of range".into());
+		}
+		if !self
+			.postings
+			.chunks_exact(Posting::SIZE)
+			.all(|posting| (posting[7] as usize) < Field::ALL.len())
+		{
+			return Err("Malformed index: posting field out of range".into());
+		}
+
+		let keyword_count = self.posting_offsets.len() / 4;
+		let map = fst::Map::new(self.fst)?;
+		let mut keywords = map.stream();
+		while let Some((_, keyword_index)) = keywords.next() {
+			if keyword_index as usize >= keyword_count {
+				return Err("Malformed index: keyword out of range".into());
+			}
+		}
+		Ok(())
+	}
+
+	/// Number of indexed documents
+	pub fn document_count(&self) -> usize {
+		self.document_categories.len() / 4
+	}
+
+	/// Number of strings stored per document: title, href, body and the stored
+	/// custom fields.
+	fn strings_per_document(&self) -> usize {
+		3 + self.stored_fields.len()
+	}
+
+	/// Number of body words of a document.
+	pub fn document_length(&self, document_index: usize) -> Option<usize> {
+		if document_index >= self.document_count() {
+			return None;
+		}
+		Some(read_u32(self.document_lengths, document_index) as usize)
+	}
+
+	/// The synonyms of a normalized word or phrase, not including itself.
+	#[cfg(any(feature = "wasm", test))]
+	fn synonyms(&self, term: &str) -> Vec<&'a str> {
+		self
+			.synonyms
+			.iter()
+			.filter(|group| group.contains(&term))
+			.flat_map(|group| group.iter().copied())
+			.filter(|synonym| *synonym != term)
+			.collect()
+	}
+
+	/// Position in `categories` of a document's category.
+	#[cfg(any(feature = "wasm", test))]
+	fn document_category(&self, document_index: usize) -> usize {
+		read_u32(self.document_categories, document_index) as usize
+	}
+
+	/// Range of the postings of the keyword at `keyword_index` in the FST.
+	#[cfg(any(feature = "wasm", test))]
+	fn posting_range(&self, keyword_index: usize) -> std::ops::Range<usize> {
+		let keyword_count = self.posting_offsets.len() / 4;
+		let start = read_u32(self.posting_offsets, keyword_index) as usize;
+		let end = if keyword_index + 1 < keyword_count {
+			read_u32(self.posting_offsets, keyword_index + 1) as usize
+		} else {
+			self.postings.len() / Posting::SIZE
+		};
+		start..end
+	}
+
+	/// The postings of the keyword at `keyword_index` in the FST.
+	#[cfg(any(feature = "wasm", test))]
+	fn postings(&self, keyword_index: usize) -> impl Iterator<Item = Posting> + 'a {
+		let range = self.posting_range(keyword_index);
+		self.postings[range.start * Posting::SIZE..range.end * Posting::SIZE]
+			.chunks_exact(Posting::SIZE)
+			.map(Posting::read)
+	}
+
+	/// Score of a posting, given the number of documents its keyword occurs in
+	/// and the fields the query may match.
+	#[cfg(any(feature = "wasm", test))]
+	fn posting_score(&self, posting: &Posting, document_frequency: usize, scope: FieldScope) -> f32 {
+		match self.ranking {
+			Ranking::Keyword => {
+				// the boost of the field the score was taken from, or the strongest one
+				// the query matched instead, e.g. for `category:` terms
+				let matched = posting.fields & scope.mask;
+				let boost = if matched & posting.origin.bit() != 0 {
+					scope.boost(posting.origin)
+				} else {
+					Field::ALL
+						.into_iter()
+						.filter(|field| matched & field.bit() != 0)
+						.map(|field| scope.boost(field))
+						.fold(0.0, f32::max)
+				};
+				posting.score as f32 / self.score_scale * boost
+			}
+			Ranking::Bm25 => {
+				let bm25 = &self.bm25;
+				let frequency: f32 = Field::ALL
+					.into_iter()
+					.filter(|field| posting.fields & scope.mask & field.bit() != 0)
+					.map(|field| {
+						let boost = bm25.boosts.boost(field) * scope.boost(field);
+						match field {
+							Field::Body => boost * posting.term_frequency.max(1) as f32,
+							_ => boost,
+						}
+					})
+					.sum();
+
+				let documents = self.document_count() as f32;
+				let document_frequency = document_frequency as f32;
+				let idf = (1.0 + (documents - document_frequency + 0.5) / (document_frequency + 0.5)).ln();
+				let length = self.document_length(posting.document).unwrap_or(0) as f32;
+				let normalization = 1.0 - bm25.b + bm25.b * length / self.average_document_length.max(1.0);
+				idf * frequency * (bm25.k1 + 1.0) / (frequency + bm25.k1 * normalization)
+			}
+		}
+	}
+
+	/// Decode a stored field of a document.
+	#[cfg(any(feature = "wasm", test))]
+	fn document_string(
+		&self,
+		document_index: usize,
+		field: Field,
+	) -> Result<String, Box<dyn std::error::Error>> {
+		let offset = match field {
+			Field::Title => 0,
+			Field::Href => 1,
+			Field::Body => 2,
+			Field::Category => {
+				if document_index >= self.document_count() {
+					return Err("Failed to get document category".into());
+				}
+				let category = self.document_category(document_index);
+				return Ok(self.categories[category].to_string());
+			}
+			Field::Keywords => return Err("Document keywords are not stored".into()),
+			Field::Custom => return Err("Custom fields are decoded by name".into()),
+		};
+		Ok(
+			self
+				.document_strings
+				.get(document_index * self.strings_per_document() + offset)
+				.ok_or("Failed to get document string")?,
+		)
+	}
+
+	/// Factor that the score of a document is multiplied by for its popularity,
+	/// from 1 for the least popular documents to `1 + weight` for the most popular.
+	#[cfg(any(feature = "wasm", test))]
+	fn popularity_boost(&self, document_index: usize, weight: f32) -> f32 {
+		match self.popularity.get(document_index) {
+			Some(popularity) => 1.0 + weight * *popularity as f32 / u8::MAX as f32,
+			None => 1.0,
+		}
+	}
+
+	/// The ranks of the documents by a sortable field, see [`SortColumn`].
+	#[cfg(any(feature = "wasm", test))]
+	fn sort_column(&self, name: &str) -> Result<&'a [u8], Box<dyn std::error::Error>> {
+		self
+			.sort_columns
+			.iter()
+			.find(|column| column.name == name)
+			.map(|column| column.ranks)
+			.ok_or_else(|| format!("Field '{}' is not sortable", name).into())
+	}
+
+	/// Decode the stored custom fields of a document, leaving out empty ones.
+	#[cfg(any(feature = "wasm", test))]
+	fn document_fields(
+		&self,
+		document_index: usize,
+	) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+		let mut fields = BTreeMap::new();
+		for (i, name) in self.stored_fields.iter().enumerate() {
+			let value = self
+				.document_strings
+				.get(document_index * self.strings_per_document() + 3 + i)
+				.ok_or("Failed to get document string")?;
+			if !value.is_empty() {
+				fields.insert(name.to_string(), value);
+			}
+		}
+		Ok(fields)
+	}
+
+	/// Whether the value of a filterable custom field of a document is one of
+	/// `values`.
+	#[cfg(any(feature = "wasm", test))]
+	fn filter_matches(
+		&self,
+		name: &str,
+		values: &[String],
+	) -> Result<impl Fn(usize) -> bool + '_, Box<dyn std::error::Error>> {
+		let field = self
+			.filter_fields
+			.iter()
+			.find(|field| field.name == name)
+			.ok_or_else(|| format!("Field '{}' is not filterable", name))?;
+		let positions: Vec<u32> = values
+			.iter()
+			.filter_map(|value| field.values.binary_search(&value.as_str()).ok())
+			.map(|position| position as u32)
+			.collect();
+		Ok(move |document_index| positions.contains(&read_u32(field.documents, document_index)))
+	}
+}
+
+/// Keywords extracted from a single document, in extraction order.
+#[cfg(any(feature = "cli", test))]
+#[derive(Default)]
+struct DocumentKeywords {
+	/// Stemmer applied to every keyword
+	stemmer: Stemmer,
+	/// Keyword, score, the field the score was taken from and bit set of the
+	/// fields it was found in
+	keywords: Vec<(String, f64, Field, u8)>,
+	positions: HashMap<String, usize>,
+}
+
+#[cfg(any(feature = "cli", test))]
+impl DocumentKeywords {
+	/// Add a keyword found in `field`. A keyword that was already extracted keeps
+	/// its original score and only records the additional field.
+	fn insert(&mut self, keyword: String, score: f64, field: Field) {
+		let term = self.stemmer.stem(&keyword).into_owned();
+		if !self.mark_term(&term, field) {
+			self.positions.insert(term.clone(), self.keywords.len());
+			self.keywords.push((term, score, field, field.bit()));
+		}
+	}
+
+	/// Record that an already extracted keyword was also found in `field`,
+	/// returning whether the keyword exists.
+	fn mark(&mut self, keyword: &str, field: Field) -> bool {
+		let stemmer = self.stemmer;
+		self.mark_term(&stemmer.stem(keyword), field)
+	}
+
+	/// Like [`DocumentKeywords::mark`] for a keyword that is already stemmed, which
+	/// must not be stemmed again: stemming `databases` gives `databas`, which
+	/// stems to `databa`.
+	fn mark_term(&mut self, term: &str, field: Field) -> bool {
+		match self.positions.get(term) {
+			Some(&position) => {
+				self.keywords[position].3 |= field.bit();
+				true
+			}
+			None => false,
+		}
+	}
+}
+
+#[cfg(any(feature = "cli", test))]
+pub fn build_index(documents: Vec<Document>) -> Result<Index, Box<dyn std::error::Error>> {
+	build_index_with_options(documents, &IndexOptions::default())
+}
+
+/// Parse the value of a custom field of every document, `None` for documents
+/// without one.
+#[cfg(any(feature = "cli", test))]
+fn parse_field_values<K>(
+	documents: &[Document],
+	schema: &FieldSchema,
+	parse: impl Fn(&str) -> Option<K>,
+) -> Result<Vec<Option<K>>, Box<dyn std::error::Error>> {
+	documents
+		.iter()
+		.map(|doc| match doc.fields.get(&schema.name) {
+			Some(value) => parse(value).map(Some).ok_or_else(|| {
+				format!(
+					"Invalid value '{}' for {:?} field '{}' of {}",
+					value, schema.kind, schema.name, doc.href
+				)
+				.into()
+			}),
+			None => Ok(None),
+		})
+		.collect()
+}
+
+/// The language of a document without its region, e.g. `en` for `en-US`.
+#[cfg(any(feature = "cli", test))]
+fn document_language(doc: &Document, options: &IndexOptions) -> String {
+	language_code(doc.language.as_deref().unwrap_or(&options.language))
+}
+
+/// A language tag without its region, e.g. `en` for `en-US`.
+#[cfg(any(feature = "cli", test))]
+fn language_code(language: &str) -> String {
+	language
+		.split(['-', '_'])
+		.next()
+		.unwrap_or("")
+		.to_lowercase()
+}
+
+/// A document a keyword occurs in, with the score of the keyword, the field the
+/// score was taken from, the fields it was found in and its term frequency.
+#[cfg(any(feature = "cli", test))]
+type KeywordOccurrence<'a> = (&'a Document, f64, Field, u8, u16);
+
+#[cfg(any(feature = "cli", test))]
+pub fn build_index_with_options(
+	documents: Vec<Document>,
+	options: &IndexOptions,
+) -> Result<Index, Box<dyn std::error::Error>> {
+	use std::collections::HashSet;
+
+	let weights = &options.weights;
+
+	let stop_list = |language: &str| {
+		STOP_WORDS
+			.iter()
+			.find(|(code, _)| *code == language)
+			.map(|(_, list)| *list)
+	};
+	let default_language = language_code(&options.language);
+	let default_list = stop_list(&default_language)
+		.ok_or_else(|| format!("Unsupported language '{}'", options.language))?;
+
+	// stop words and keyword extractor per language, documents in a language
+	// without a bundled list use those of the default language
+	let mut extractors: HashMap<String, (rake::StopWords, rake::Rake)> = HashMap::new();
+	for doc in &documents {
+		let language = document_language(doc, options);
+		if extractors.contains_key(&language) {
+			continue;
+		}
+
+		let list = stop_list(&language).unwrap_or_else(|| {
+			eprintln!(
+				"[docfind] Warning: no stop words for language '{}' of {}, using '{}'",
+				language, doc.href, default_language
+			);
+			default_list
+		});
+		// keyword extraction sees the original words, everything else folded ones
+		let stop_words: HashSet<String> = parse_stop_words(list)
+			.chain(options.stop_words.iter().map(|w| w.to_lowercase()))
+			.flat_map(|w| [tokenize::fold(&w), w])
+			.collect();
+		let sw = rake::StopWords::from(stop_words);
+		let rake = rake::Rake::new(sw.clone());
+		extractors.insert(language, (sw, rake));
+	}
+
+	let mut strings: Vec<&str> = Vec::new();
+	let mut keywords_to_documents: HashMap<String, Vec<KeywordOccurrence>> = HashMap::new();
+	let mut doc_index_map: HashMap<&str, usize> = HashMap::new();
+
+	let categories: Vec<String> = documents
+		.iter()
+		.map(|doc| doc.category.clone())
+		.collect::<std::collections::BTreeSet<String>>()
+		.into_iter()
+		.collect();
+	let mut document_categories: Vec<u8> = Vec::with_capacity(documents.len() * 4);
+	let mut document_lengths: Vec<u8> = Vec::with_capacity(documents.len() * 4);
+
+	for (doc_index, doc) in documents.iter().enumerate() {
+		doc_index_map.insert(&doc.href, doc_index);
+		strings.push(&doc.title);
+		strings.push(&doc.href);
+		strings.push(&doc.body);
+		for schema in options.fields.iter().filter(|schema| schema.stored) {
+			strings.push(doc.fields.get(&schema.name).map_or("", String::as_str));
+		}
+		let category = categories.binary_search(&doc.category).unwrap() as u32;
+		document_categories.extend_from_slice(&category.to_le_bytes());
+
+		let body_words = Field::Body.words(&doc.body);
+		document_lengths.extend_from_slice(&(body_words.len() as u32).to_le_bytes());
+		let body_terms: Vec<Cow<str>> = body_words.iter().map(|w| options.stemmer.stem(w)).collect();
+		let mut body_counts: HashMap<&str, u16> = HashMap::new();
+		for term in &body_terms {
+			let count = body_counts.entry(term).or_default();
+			*count = count.saturating_add(1);
+		}
+		// occurrences of a keyword, which may be a phrase, in the body
+		let term_frequency = |keyword: &str| -> u16 {
+			let words: Vec<&str> = keyword.split(' ').collect();
+			if words.len() == 1 {
+				return body_counts.get(keyword).copied().unwrap_or(0);
+			}
+			let count = body_terms
+				.windows(words.len())
+				.filter(|window| window.iter().zip(&words).all(|(a, b)| a == b))
+				.count();
+			count.min(u16::MAX as usize) as u16
+		};
+
+		let (sw, rake) = &extractors[&document_language(doc, options)];
+
+		let mut keywords = DocumentKeywords {
+			stemmer: options.stemmer,
+			..Default::default()
+		};
+
+		// Add explicit keywords from document metadata
+		if let Some(kw) = &doc.keywords {
+			for k in kw {
+				let keyword = tokenize::words(k).join(" ");
+				if !keyword.is_empty() && !sw.contains(&keyword.clone()) {
+					keywords.insert(keyword, weights.keywords, Field::Keywords);
+				}
+			}
+		}
+
+		// add keywords from title
+		let title_keywords = Field::Title
+			.words(&doc.title)
+			.into_iter()
+			.filter(|w| !sw.contains(&w.clone()))
+			.collect::<HashSet<String>>(); // deduplicate
+
+		for tk in title_keywords {
+			keywords.insert(tk, weights.title, Field::Title);
+		}
+
+		// identifiers are indexed whole and by their parts, which keyword extraction
+		// would otherwise mangle
+		for (text, score, field) in [
+			(&doc.title, weights.title, Field::Title),
+			(&doc.body, weights.identifiers, Field::Body),
+		] {
+			for (identifier, parts) in text.split_whitespace().filter_map(tokenize::identifier) {
+				for keyword in std::iter::once(identifier).chain(parts) {
+					if !sw.contains(&keyword) {
+						keywords.insert(keyword, score, field);
+					}
+				}
+			}
+		}
+
+		let body_keywords = rake.run_fragments(vec![doc.body.as_str()]);
+		// remaining budget per phrase length, single words first
+		let mut budgets: Vec<usize> = (0..options.max_phrase_length)
+			.map(|i| {
+				options
+					.body_budgets
+					.get(i)
+					.or(options.body_budgets.last())
+					.copied()
+					.unwrap_or(0)
+			})
+			.collect();
+
+		for k in &body_keywords {
+			if budgets.iter().all(|budget| *budget == 0) {
+				break;
+			}
+
+			let words = tokenize::words(&k.keyword);
+			if words.is_empty() {
+				continue;
+			}
+			let keyword = words.join(" ");
+
+			// continue if keyword is already in title keywords
+			if keywords.mark(&keyword, Field::Body) {
+				continue;
+			}
+
+			match budgets.get_mut(words.len() - 1) {
+				Some(budget) if *budget > 0 => *budget -= 1,
+				_ => continue,
+			}
+
+			keywords.insert(keyword, k.score * weights.body, Field::Body);
+		}
+
+		// keyword extraction splits phrases on spaces and punctuation, which text
+		// written without spaces lacks, so its bigrams are indexed like full text
+		for (word, term) in body_words.iter().zip(&body_terms) {
+			if (options.full_text || word.chars().all(tokenize::is_cjk)) && !sw.contains(word) {
+				let score = weights.body * body_counts[term.as_ref()] as f64;
+				keywords.insert(word.clone(), score, Field::Body);
+			}
+		}
+
+		for schema in options.fields.iter().filter(|schema| schema.searchable) {
+			let Some(value) = doc.fields.get(&schema.name) else {
+				continue;
+			};
+			for word in Field::Custom.words(value) {
+				if !sw.contains(&word) {
+					keywords.insert(word, schema.weight, Field::Custom);
+				}
+			}
+		}
+
+		// category and href keywords are only matched by field-scoped queries
+		let mut category_keywords = Field::Category.words(&doc.category);
+		if category_keywords.len() > 1 {
+			category_keywords.push(category_keywords.join(" "));
+		}
+		for (keyword, score, field) in category_keywords
+			.into_iter()
+			.map(|k| (k, weights.category, Field::Category))
+			.chain(
+				Field::Href
+					.words(&doc.href)
+					.into_iter()
+					.map(|k| (k, weights.href, Field::Href)),
+			) {
+			if !sw.contains(&keyword.clone()) {
+				keywords.insert(keyword, score, field);
+			}
+		}
+
+		for (keyword, score, origin, fields) in keywords.keywords {
+			let term_frequency = term_frequency(&keyword);
+			keywords_to_documents.entry(keyword).or_default().push((
+				doc,
+				score,
+				origin,
+				fields,
+				term_frequency,
+			));
+		}
+	}
+
+	println!("Extracted {} unique keywords", keywords_to_documents.len());
+
+	// keep integer weights exact by scaling with a power of two
+	let max_score = keywords_to_documents
+		.values()
+		.flatten()
+		.map(|(_, score, _, _, _)| *score)
+		.fold(0.0, f64::max);
+	let score_scale = if max_score > 0.0 {
+		2f64.powi((u16::MAX as f64 / max_score).log2().floor() as i32)
+	} else {
+		1.0
+	};
+
+	let mut fst_builder = fst::MapBuilder::memory();
+	let mut keyword_to_documents: Vec<Vec<Posting>> = Vec::new();
+	let mut keywords: Vec<String> = keywords_to_documents.keys().cloned().collect();
+	keywords.sort();
+
+	for (index, keyword) in keywords.iter().enumerate() {
+		fst_builder.insert(keyword, index as u64)?;
+
+		let mut doc_scores = keywords_to_documents.get(keyword).unwrap().clone();
+		doc_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+		let entry = doc_scores
+			.iter()
+			.map(|(doc, score, origin, fields, term_frequency)| Posting {
+				document: doc_index_map[doc.href.as_str()],
+				score: (score * score_scale).round() as u16,
+				fields: *fields,
+				origin: *origin,
+				term_frequency: *term_frequency,
+			})
+			.collect::<Vec<Posting>>();
+
+		keyword_to_documents.push(entry);
+	}
+
+	let synonyms: Vec<Vec<String>> = options
+		.synonyms
+		.iter()
+		.map(|group| {
+			let mut normalized: Vec<String> = Vec::new();
+			for synonym in group {
+				let synonym = options
+					.stemmer
+					.stem(&tokenize::words(synonym).join(" "))
+					.into_owned();
+				if !synonym.is_empty() && !normalized.contains(&synonym) {
+					normalized.push(synonym);
+				}
+			}
+			normalized
+		})
+		.filter(|group| group.len() > 1)
+		.collect();
+
+	let fst = fst_builder.into_inner().unwrap();
+	let document_strings = FsstStrVec::from_strings(&strings);
+	let (posting_offsets, postings) = encode_postings(&keyword_to_documents);
+	let total_length: u64 = document_lengths
+		.chunks_exact(4)
+		.map(|length| u32::from_le_bytes(length.try_into().unwrap()) as u64)
+		.sum();
+	let average_document_length = total_length as f32 / documents.len().max(1) as f32;
+
+	let filter_fields = options
+		.fields
+		.iter()
+		.filter(|schema| schema.filterable && schema.kind == FieldKind::Text)
+		.map(|schema| {
+			let values: Vec<String> = documents
+				.iter()
+				.filter_map(|doc| doc.fields.get(&schema.name).cloned())
+				.collect::<std::collections::BTreeSet<String>>()
+				.into_iter()
+				.collect();
+			let positions = documents
+				.iter()
+				.flat_map(|doc| {
+					let position = doc.fields.get(&schema.name).map_or(u32::MAX, |value| {
+						values.binary_search(value).unwrap() as u32
+					});
+					position.to_le_bytes()
+				})
+				.collect();
+			FilterField {
+				name: schema.name.clone(),
+				values,
+				documents: positions,
+			}
+		})
+		.collect();
+
+	let mut attributes: Vec<Attribute> = Vec::new();
+	for schema in options.fields.iter().filter(|schema| schema.filterable) {
+		if schema.kind == FieldKind::Text {
+			continue;
+		}
+		// versions are stored by their position among the distinct versions, which
+		// keeps their order but not their value
+		let (versions, values) = if schema.kind == FieldKind::Version {
+			let parsed = parse_field_values(&documents, schema, FieldKind::version)?;
+			let distinct: Vec<&Vec<u64>> = parsed
+				.iter()
+				.flatten()
+				.collect::<std::collections::BTreeSet<_>>()
+				.into_iter()
+				.collect();
+			let versions = distinct
+				.iter()
+				.map(|version| {
+					version
+						.iter()
+						.map(u64::to_string)
+						.collect::<Vec<_>>()
+						.join(".")
+				})
+				.collect();
+			let values = parsed
+				.iter()
+				.map(|version| {
+					version
+						.as_ref()
+						.map(|version| distinct.binary_search(&version).unwrap() as f64)
+				})
+				.collect();
+			(versions, values)
+		} else {
+			let values = parse_field_values(&documents, schema, |value| schema.kind.parse(value))?;
+			(Vec::new(), values)
+		};
+		attributes.push(Attribute {
+			name: schema.name.clone(),
+			kind: schema.kind,
+			versions,
+			values: values
+				.into_iter()
+				.flat_map(|value| value.unwrap_or(f64::NAN).to_le_bytes())
+				.collect(),
+		});
+	}
+
+	// popularity on a logarithmic scale, so that a few very popular pages do not
+	// flatten the differences between all others
+	let max_popularity = documents
+		.iter()
+		.filter_map(|doc| doc.popularity)
+		.fold(0.0, f64::max);
+	let popularity: Vec<u8> = if max_popularity > 0.0 {
+		documents
+			.iter()
+			.map(|doc| {
+				let popularity = doc.popularity.unwrap_or(0.0).max(0.0);
+				(popularity.ln_1p() / max_popularity.ln_1p() * u8::MAX as f64).round() as u8
+			})
+			.collect()
+	} else {
+		Vec::new()
+	};
+
+	// titles are always sortable, custom fields when declared so
+	let mut sort_columns = vec![SortColumn::new(
+		"title",
+		documents
+			.iter()
+			.map(|doc| Some(tokenize::fold(&doc.title)))
+			.collect(),
+	)];
+	for schema in options.fields.iter().filter(|schema| schema.sortable) {
+		let values = documents.iter().map(|doc| doc.fields.get(&schema.name));
+		let column = match schema.kind {
+			FieldKind::Text => SortColumn::new(
+				&schema.name,
+				values
+					.map(|value| value.map(|value| tokenize::fold(value)))
+					.collect(),
+			),
+			FieldKind::Version => SortColumn::new(
+				&schema.name,
+				parse_field_values(&documents, schema, FieldKind::version)?,
+			),
+			kind => SortColumn::new(
+				&schema.name,
+				parse_field_values(&documents, schema, |value| kind.parse(value))?,
+			),
+		};
+		sort_columns.push(column);
+	}
+
+	Ok(Index {
+		fst,
+		document_strings,
+		categories,
+		document_categories,
+		document_lengths,
+		posting_offsets,
+		postings,
+		ranking: options.ranking,
+		bm25: options.bm25,
+		average_document_length,
+		score_scale: score_scale as f32,
+		stemmer: options.stemmer,
+		synonyms,
+		stored_fields: options
+			.fields
+			.iter()
+			.filter(|schema| schema.stored)
+			.map(|schema| schema.name.clone())
+			.collect(),
+		filter_fields,
+		attributes,
+		sort_columns,
+		popularity,
+	})
+}
+
+/// Per-document aggregated score and matched keywords.
+#[cfg(any(feature = "wasm", test))]
+type DocumentMatches = HashMap<usize, (f32, Vec<KeywordMatch>)>;
+
+/// An index keyword, its position in the FST, how it matched and with how
+/// many typos.
+#[cfg(any(feature = "wasm", test))]
+type MatchedKeyword = (String, u64, MatchType, u32);
+
+/// Levenshtein distance between two words, in characters.
+#[cfg(any(feature = "wasm", test))]
+fn edit_distance(a: &str, b: &str) -> u32 {
+	let b: Vec<char> = b.chars().collect();
+	let mut row: Vec<u32> = (0..=b.len() as u32).collect();
+	for (i, ca) in a.chars().enumerate() {
+		let mut previous = row[0];
+		row[0] = i as u32 + 1;
+		for (j, cb) in b.iter().enumerate() {
+			let substitution = previous + (ca != *cb) as u32;
+			previous = row[j + 1];
+			row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+		}
+	}
+	row[b.len()]
+}
+
+/// Find the index keywords matching `word`, either exactly, as a prefix or
+/// within the number of typos allowed for its length.
+#[cfg(any(feature = "wasm", test))]
+fn find_keywords(
+	map: &fst::Map<&[u8]>,
+	word: &str,
+	typo_tolerance: Option<&TypoTolerance>,
+) -> Result<Vec<MatchedKeyword>, Box<dyn std::error::Error>> {
+	use fst::automaton::{Levenshtein, Str};
+	use fst::map::OpBuilder;
+	use fst::{Automaton, Streamer};
+
+	let mut op = OpBuilder::new().add(map.search(Str::new(word).starts_with()));
+	let typos = typo_tolerance.map_or(0, |tolerance| tolerance.typos(word));
+	if typos > 0 {
+		// the automaton for two typos can outgrow its state limit on long words
+		let automaton = Levenshtein::new(word, typos).or_else(|_| Levenshtein::new(word, 1))?;
+		op = op.add(map.search(automaton));
+	}
+	let mut stream = op.union();
+
+	let mut keywords = Vec::new();
+	while let Some((keyword, indexed_value)) = stream.next() {
+		let keyword = String::from_utf8(keyword.to_vec())?;
+		let keyword_index = indexed_value
+			.first()
+			.ok_or("Missing keyword index in FST")?
+			.value;
+		let (match_type, typos) = if keyword == word {
+			(MatchType::Exact, 0)
+		} else if keyword.starts_with(word) {
+			(MatchType::Prefix, 0)
+		} else {
+			(MatchType::Fuzzy, edit_distance(word, &keyword))
+		};
+		keywords.push((keyword, keyword_index, match_type, typos));
+	}
+
+	Ok(keywords)
+}
+
+/// The fields a query term may match and how much each of them counts.
+#[cfg(any(feature = "wasm", test))]
+#[derive(Debug, Clone, Copy)]
+struct FieldScope<'a> {
+	/// Bit set of the fields the term may match
+	mask: u8,
+	/// See [`SearchOptions::field_boosts`]
+	boosts: &'a BTreeMap<Field, f32>,
+}
+
+#[cfg(any(feature = "wasm", test))]
+impl FieldScope<'_> {
+	fn boost(&self, field: Field) -> f32 {
+		self.boosts.get(&field).copied().unwrap_or(1.0)
+	}
+}
+
+/// Add the postings of a matched keyword to `matches`, skipping documents where
+/// the keyword was not found in any of the fields of `scope`.
+#[cfg(any(feature = "wasm", test))]
+fn add_keyword_postings(
+	index: &IndexView,
+	matches: &mut DocumentMatches,
+	keyword: &str,
+	keyword_index: u64,
+	match_type: MatchType,
+	scope: FieldScope,
+	weight: f32,
+) {
+	let document_frequency = index.posting_range(keyword_index as usize).len();
+	for posting in index.postings(keyword_index as usize) {
+		if posting.fields & scope.mask == 0 {
+			continue;
+		}
+
+		let (total, keyword_matches) = matches.entry(posting.document).or_default();
+		*total += weight * index.posting_score(&posting, document_frequency, scope);
+
+		// a keyword can be reached from several query words, keep its strongest match
+		match keyword_matches.iter_mut().find(|m| m.keyword == keyword) {
+			Some(existing) => existing.match_type = existing.match_type.min(match_type),
+			None => keyword_matches.push(KeywordMatch {
+				keyword: keyword.to_string(),
+				match_type,
+				term_frequency: posting.term_frequency,
+			}),
+		}
+	}
+}
+
+/// Merge the matches of one clause into the matches of the whole query.
+#[cfg(any(feature = "wasm", test))]
+fn merge_matches(into: &mut DocumentMatches, from: DocumentMatches) {
+	for (document_index, (score, keyword_matches)) in from {
+		let (total, existing) = into.entry(document_index).or_default();
+		*total += score;
+		for keyword_match in keyword_matches {
+			match existing
+				.iter_mut()
+				.find(|m| m.keyword == keyword_match.keyword)
+			{
+				Some(m) => m.match_type = m.match_type.min(keyword_match.match_type),
+				None => existing.push(keyword_match),
+			}
+		}
+	}
+}
+
+/// Whether a field of a document contains `words` consecutively. Without a field
+/// the title and body are checked.
+#[cfg(any(feature = "wasm", test))]
+fn contains_phrase(
+	index: &IndexView,
+	document_index: usize,
+	field: Option<Field>,
+	words: &[String],
+) -> Result<bool, Box<dyn std::error::Error>> {
+	let fields: &[Field] = match field {
+		None => &[Field::Title, Field::Body],
+		// explicit keywords are not stored and custom fields not necessarily, only
+		// whole keywords can match
+		Some(Field::Keywords | Field::Custom) => &[],
+		Some(ref field) => std::slice::from_ref(field),
+	};
+
+	for field in fields {
+		let text = index.document_string(document_index, *field)?;
+		let terms: Vec<String> = field
+			.words(&text)
+			.iter()
+			.map(|word| index.stemmer.stem(word).into_owned())
+			.collect();
+		if terms.windows(words.len()).any(|w| w == words) {
+			return Ok(true);
+		}
+	}
+	Ok(false)
+}
+
+/// How far a query word may be expanded to other index keywords.
+#[cfg(any(feature = "wasm", test))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Expansion {
+	/// Only the word itself, or multi-word keywords starting with it
+	Exact,
+	/// Also keywords the word is a prefix of
+	Prefix,
+	/// Also keywords within the typo tolerance
+	Fuzzy,
+}
+
+/// Find the documents matching a single query term.
+#[cfg(any(feature = "wasm", test))]
+fn match_term(
+	index: &IndexView,
+	map: &fst::Map<&[u8]>,
+	field: Option<Field>,
+	term: &query::Term,
+	options: &SearchOptions,
+	expansion: Expansion,
+) -> Result<DocumentMatches, Box<dyn std::error::Error>> {
+	let scope = FieldScope {
+		mask: field.map_or(Field::DEFAULT_MASK, Field::bit),
+		boosts: &options.field_boosts,
+	};
+	let mut matches = DocumentMatches::new();
+
+	match term {
+		query::Term::Word(word) => {
+			let typo_tolerance = options
+				.typo_tolerance
+				.as_ref()
+				.filter(|_| expansion == Expansion::Fuzzy);
+			for (keyword, keyword_index, match_type, typos) in find_keywords(map, word, typo_tolerance)? {
+				if expansion == Expansion::Exact
+					&& match_type == MatchType::Prefix
+					&& !keyword[word.len()..].starts_with(' ')
+				{
+					continue;
+				}
+				add_keyword_postings(
+					index,
+					&mut matches,
+					&keyword,
+					keyword_index,
+					match_type,
+					scope,
+					options.match_weight(match_type, typos),
+				);
+			}
+		}
+		query::Term::Phrase(words) => {
+			// keywords holding the whole phrase, e.g. multi-word RAKE keywords
+			let phrase = words.join(" ");
+			for (keyword, keyword_index, match_type, _) in find_keywords(map, &phrase, None)? {
+				if match_type == MatchType::Exact || keyword[phrase.len()..].starts_with(' ') {
+					add_keyword_postings(
+						index,
+						&mut matches,
+						&keyword,
+						keyword_index,
+						match_type,
+						scope,
+						options.match_weight(match_type, 0),
+					);
+				}
+			}
+
+			// documents holding every indexed word of the phrase as a keyword, verified
+			// against their text since stop words and word order are not indexed
+			let mut candidates: Option<DocumentMatches> = None;
+			for word in words {
+				let Some(keyword_index) = map.get(word) else {
+					continue;
+				};
+				let mut word_matches = DocumentMatches::new();
+				add_keyword_postings(
+					index,
+					&mut word_matches,
+					word,
+					keyword_index,
+					MatchType::Exact,
+					scope,
+					1.0,
+				);
+				candidates = Some(match candidates {
+					None => word_matches,
+					Some(mut candidates) => {
+						candidates.retain(|document_index, _| word_matches.contains_key(document_index));
+						word_matches.retain(|document_index, _| candidates.contains_key(document_index));
+						merge_matches(&mut candidates, word_matches);
+						candidates
+					}
+				});
+			}
+
+			let mut verified = DocumentMatches::new();
+			for (document_index, entry) in candidates.unwrap_or_default() {
+				if !matches.contains_key(&document_index)
+					&& contains_phrase(index, document_index, field, words)?
+				{
+					verified.insert(document_index, entry);
+				}
+			}
+			merge_matches(&mut matches, verified);
+		}
+	}
+
+	Ok(matches)
+}
+
+/// Find the documents matching a single query term or one of its synonyms.
+#[cfg(any(feature = "wasm", test))]
+fn match_synonyms(
+	index: &IndexView,
+	map: &fst::Map<&[u8]>,
+	field: Option<Field>,
+	term: &query::Term,
+	options: &SearchOptions,
+	expansion: Expansion,
+) -> Result<DocumentMatches, Box<dyn std::error::Error>> {
+	let mut matches = match_term(index, map, field, term, options, expansion)?;
+	let text = match term {
+		query::Term::Word(word) => word.clone(),
+		query::Term::Phrase(words) => words.join(" "),
+	};
+	for synonym in index.synonyms(&text) {
+		let mut words: Vec<String> = synonym.split(' ').map(str::to_string).collect();
+		let synonym = match words.len() {
+			1 => query::Term::Word(words.remove(0)),
+			_ => query::Term::Phrase(words),
+		};
+		// typos are only tolerated in what was typed
+		merge_matches(
+			&mut matches,
+			match_term(
+				index,
+				map,
+				field,
+				&synonym,
+				options,
+				expansion.min(Expansion::Prefix),
+			)?,
+		);
+	}
+	Ok(matches)
+}
+
+/// Search the index, returning at most `max_results` hits ranked by score.
+///
+/// See [`query::parse`] for the supported query syntax.
+#[cfg(any(feature = "wasm", test))]
+pub fn search(
+	index: &IndexView,
+	query: &str,
+	max_results: usize,
+) -> Result<Vec<SearchHit>, Box<dyn std::error::Error>> {
+	let options = SearchOptions {
+		max_results,
+		..Default::default()
+	};
+	Ok(search_with_options(index, query, &options)?.hits)
+}
+
+/// Search the index with additional filtering and faceting options.
+#[cfg(any(feature = "wasm", test))]
+pub fn search_with_options(
+	index: &IndexView,
+	query: &str,
+	options: &SearchOptions,
+) -> Result<SearchResults, Box<dyn std::error::Error>> {
+	use query::Occur;
+	use std::collections::HashSet;
+
+	let map = fst::Map::new(index.fst)?;
+
+	let mut documents = DocumentMatches::new();
+	// documents matching every required clause of a group, or any clause of a
+	// group without required ones
+	let mut accepted: HashSet<usize> = HashSet::new();
+	let mut excluded: HashSet<usize> = HashSet::new();
+
+	for group in query::parse(query, index.stemmer).groups {
+		let mut required: Option<HashSet<usize>> = None;
+		let mut optional: HashSet<usize> = HashSet::new();
+		for clause in group {
+			match clause.occur {
+				Occur::Should => {
+					let matches = match_synonyms(
+						index,
+						&map,
+						clause.field,
+						&clause.term,
+						options,
+						Expansion::Fuzzy,
+					)?;
+					optional.extend(matches.keys());
+					merge_matches(&mut documents, matches);
+				}
+				Occur::Must => {
+					let matches = match_synonyms(
+						index,
+						&map,
+						clause.field,
+						&clause.term,
+						options,
+						Expansion::Fuzzy,
+					)?;
+					required = Some(match required {
+						None => matches.keys().copied().collect(),
+						Some(required) => required
+							.into_iter()
+							.filter(|document_index| matches.contains_key(document_index))
+							.collect(),
+					});
+					merge_matches(&mut documents, matches);
+				}
+				Occur::MustNot => {
+					// only exclude whole words, `-api` should not drop `apis` and `-deprecated`
+					// not `depreciation`
+					let matches = match_synonyms(
+						index,
+						&map,
+						clause.field,
+						&clause.term,
+						options,
+						Expansion::Exact,
+					)?;
+					excluded.extend(matches.into_keys());
+				}
+			}
+		}
+		accepted.extend(required.unwrap_or(optional));
+	}
+
+	documents.retain(|document_index, _| {
+		!excluded.contains(document_index) && accepted.contains(document_index)
+	});
+
+	for (name, values) in &options.filters {
+		let matches = index.filter_matches(name, values)?;
+		documents.retain(|document_index, _| matches(*document_index));
+	}
+	if let Some(expression) = &options.filter {
+		let matches = filter::parse(expression)?.compile(index)?;
+		documents.retain(|document_index, _| matches(*document_index));
+	}
+
+	let mut facets: Vec<CategoryFacet> = Vec::new();
+	if options.facets {
+		let mut counts = vec![0; index.categories.len()];
+		for document_index in documents.keys() {
+			counts[index.document_category(*document_index)] += 1;
+		}
+		facets = counts
+			.into_iter()
+			.enumerate()
+			.filter(|(_, count)| *count > 0)
+			.map(|(category, count)| CategoryFacet {
+				category: index.categories[category].to_string(),
+				count,
+			})
+			.collect();
+		facets.sort_by(|a, b| {
+			b.count
+				.cmp(&a.count)
+				.then_with(|| a.category.cmp(&b.category))
+		});
+	}
+
+	if let Some(categories) = &options.categories {
+		let categories: HashSet<usize> = categories
+			.iter()
+			.filter_map(|category| index.categories.binary_search(&category.as_str()).ok())
+			.collect();
+		documents
+			.retain(|document_index, _| categories.contains(&index.document_category(*document_index)));
+	}
+
+	let total = documents.len();
+
+	for (document_index, (score, _)) in documents.iter_mut() {
+		*score *= index.popularity_boost(*document_index, options.popularity_weight);
+	}
+
+	let sort_columns = options
+		.sort
+		.iter()
+		.map(|key| Ok((index.sort_column(&key.field)?, key.descending)))
+		.collect::<Result<Vec<(&[u8], bool)>, Box<dyn std::error::Error>>>()?;
+	// documents without a value come last in either direction
+	let by_sort_keys = |a: usize, b: usize| {
+		sort_columns.iter().fold(
+			std::cmp::Ordering::Equal,
+			|ordering, (ranks, descending)| {
+				ordering.then_with(|| {
+					let (a, b) = (read_u32(ranks, a), read_u32(ranks, b));
+					let ordering = if *descending { b.cmp(&a) } else { a.cmp(&b) };
+					(a == u32::MAX).cmp(&(b == u32::MAX)).then(ordering)
+				})
+			},
+		)
+	};
+
+	// sort documents by the sort keys, then by score (descending), by their
+	// strongest match type and by document index (ascending) for stable ordering
+	let mut documents: Vec<(usize, (f32, Vec<KeywordMatch>))> = documents.into_iter().collect();
+	let best_match = |matches: &[KeywordMatch]| matches.iter().map(|m| m.match_type).min();
+	let compare = |a: &(usize, (f32, Vec<KeywordMatch>)), b: &(usize, (f32, Vec<KeywordMatch>))| {
+		by_sort_keys(a.0, b.0)
+			.then_with(|| b.1.0.total_cmp(&a.1.0))
+			.then_with(|| best_match(&a.1.1).cmp(&best_match(&b.1.1)))
+			.then_with(|| a.0.cmp(&b.0))
+	};
+
+	// only the requested page needs to be fully sorted
+	let end = options.offset.saturating_add(options.max_results);
+	if end < documents.len() {
+		documents.select_nth_unstable_by(end, compare);
+		documents.truncate(end);
+	}
+	documents.sort_by(compare);
+
+	let mut hits: Vec<SearchHit> = Vec::new();
+
+	for (document_index, (score, mut matches)) in documents.into_iter().skip(options.offset) {
+		matches.sort_by_key(|m| m.match_type);
+
+		let snippet = match &options.snippet {
+			Some(snippet_options) => {
+				let body = index.document_string(document_index, Field::Body)?;
+				let keywords: Vec<&str> = matches.iter().map(|m| m.keyword.as_str()).collect();
+				Some(snippet::build_snippet(
+					&body,
+					&keywords,
+					index.stemmer,
+					snippet_options,
+				))
+			}
+			None => None,
+		};
+
+		let document = match options.mode {
+			ResultMode::Documents => {
+				let fields: &[Field] = match snippet {
+					Some(_) => &[Field::Title, Field::Category, Field::Href, Field::Custom],
+					None => &[
+						Field::Title,
+						Field::Category,
+						Field::Href,
+						Field::Body,
+						Field::Custom,
+					],
+				};
+				Some(get_document(index, document_index, fields)?)
+			}
+			ResultMode::Ids => None,
+		};
+
+		hits.push(SearchHit {
+			id: document_index,
+			document,
+			score,
+			matches,
+			snippet,
+		});
+	}
+
+	Ok(SearchResults {
+		hits,
+		total,
+		facets,
+	})
+}
+
+/// Decode the requested fields of a document, leaving the others empty.
+///
+/// Explicit keywords are not stored in the index and are never returned.
+/// [`Field::Custom`] decodes all stored custom fields.
+#[cfg(any(feature = "wasm", test))]
+pub fn get_document(
+	index: &IndexView,
+	id: usize,
+	fields: &[Field],
+) -> Result<Document, Box<dyn std::error::Error>> {
+	if id >= index.document_count() {
+		return Err(format!("Document {} not found", id).into());
+	}
+
+	let mut document = Document::default();
+	for field in fields {
+		let value = match field {
+			Field::Title => &mut document.title,
+			Field::Category => &mut document.category,
+			Field::Href => &mut document.href,
+			Field::Body => &mut document.body,
+			Field::Custom => {
+				document.fields = index.document_fields(id)?;
+				continue;
+			}
+			Field::Keywords => continue,
+		};
+		*value = index.document_string(id, *field)?;
 	}
 
-	Ok(result)
+	Ok(document)
 }
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests;
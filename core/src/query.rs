@@ -0,0 +1,217 @@
+use crate::{Field, Stemmer, tokenize};
+
+/// Whether documents must, may or must not match a clause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Occur {
+	/// Optional, matching documents rank higher (the default)
+	Should,
+	/// Required, written as `+term` or joined with `AND`
+	Must,
+	/// Excluded, written as `-term`
+	MustNot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Term {
+	/// A single normalized word
+	Word(String),
+	/// A `"quoted phrase"` of normalized words
+	Phrase(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Clause {
+	pub occur: Occur,
+	/// Field the term is restricted to, written as `field:term`
+	pub field: Option<Field>,
+	pub term: Term,
+}
+
+/// A parsed query: documents match when they match one of the groups, which
+/// are joined with `OR`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Query {
+	pub groups: Vec<Vec<Clause>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+	Term(Option<Occur>, Option<Field>, Term),
+	And,
+	Or,
+}
+
+/// The stemmed words of `text` as a single word or a phrase, split like the
+/// keywords of `field` were when the index was built.
+fn term(text: &str, field: Option<Field>, stemmer: Stemmer) -> Option<Term> {
+	let words = match field {
+		Some(field) => field.words(text),
+		None => tokenize::words(text),
+	};
+	let mut words: Vec<String> = words
+		.iter()
+		.map(|word| stemmer.stem(word).into_owned())
+		.collect();
+	match words.len() {
+		0 => None,
+		1 => words.pop().map(Term::Word),
+		_ => Some(Term::Phrase(words)),
+	}
+}
+
+fn tokenize(query: &str, stemmer: Stemmer) -> Vec<Token> {
+	let mut tokens = Vec::new();
+	let mut chars = query.chars().peekable();
+
+	loop {
+		while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+		let Some(&first) = chars.peek() else {
+			break;
+		};
+
+		let occur = match first {
+			'+' => Some(Occur::Must),
+			'-' => Some(Occur::MustNot),
+			_ => None,
+		};
+		if occur.is_some() {
+			chars.next();
+		}
+
+		// a known field name followed by a colon scopes the term to that field
+		let mut raw = String::new();
+		while let Some(c) = chars.next_if(|c| c.is_alphabetic()) {
+			raw.push(c);
+		}
+		let field = match Field::from_name(&raw) {
+			Some(field) if chars.next_if_eq(&':').is_some() => {
+				raw.clear();
+				Some(field)
+			}
+			_ => None,
+		};
+
+		if raw.is_empty() && chars.next_if_eq(&'"').is_some() {
+			let mut phrase = String::new();
+			while let Some(c) = chars.next_if(|c| *c != '"') {
+				phrase.push(c);
+			}
+			chars.next(); // closing quote, if any
+
+			if let Some(term) = term(&phrase, field, stemmer) {
+				tokens.push(Token::Term(occur, field, term));
+			}
+			continue;
+		}
+
+		while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+			raw.push(c);
+		}
+
+		match (occur, field, raw.as_str()) {
+			(None, None, "AND") => tokens.push(Token::And),
+			(None, None, "OR") => tokens.push(Token::Or),
+			_ => {
+				// identifiers in titles and bodies are indexed whole, other words that
+				// are split apart, e.g. `well-known`, CJK text or `href:docs/install`,
+				// are matched as phrases
+				let identifier = match field {
+					None | Some(Field::Title | Field::Body) => tokenize::identifier(&raw),
+					Some(_) => None,
+				};
+				let term = match identifier {
+					Some((identifier, _)) => Some(Term::Word(stemmer.stem(&identifier).into_owned())),
+					None => term(&raw, field, stemmer),
+				};
+				if let Some(term) = term {
+					tokens.push(Token::Term(occur, field, term));
+				}
+			}
+		}
+	}
+
+	tokens
+}
+
+/// Parse a search query into clauses.
+///
+/// Supported syntax:
+/// - `word` matches documents optionally; at least one optional clause must match
+///   when there are no required ones
+/// - `"quoted phrase"` matches the words as a consecutive phrase
+/// - `+word` requires a match, `-word` excludes documents containing the
+///   whole word, whichever side of an `OR` it is on
+/// - `a AND b` requires both sides, `a OR b` matches either side, where `AND`
+///   binds tighter, so `a AND b OR c` matches documents with both `a` and `b`,
+///   or with `c`
+/// - `title:word` or `category:"quoted phrase"` only matches keywords from that
+///   field, one of `title`, `category`, `href`, `body`, `keywords` or `custom`
+///
+/// A query that uses none of the above is also matched as a whole, so that
+/// multi-word keywords such as `vs code` are found.
+///
+/// Words are stemmed with `stemmer`, which must be the one the index was built with.
+pub(crate) fn parse(query: &str, stemmer: Stemmer) -> Query {
+	let mut groups: Vec<Vec<Clause>> = vec![Vec::new()];
+	let mut has_syntax = false;
+	let mut pending_and = false;
+
+	for token in tokenize(query, stemmer) {
+		let clauses = groups.last_mut().unwrap();
+		match token {
+			Token::And => {
+				has_syntax = true;
+				pending_and = true;
+				// `AND` also makes the preceding clause required
+				if let Some(last) = clauses.last_mut()
+					&& last.occur == Occur::Should
+				{
+					last.occur = Occur::Must;
+				}
+			}
+			Token::Or => {
+				has_syntax = true;
+				pending_and = false;
+				if !clauses.is_empty() {
+					groups.push(Vec::new());
+				}
+			}
+			Token::Term(occur, field, term) => {
+				has_syntax |= occur.is_some() || field.is_some() || matches!(term, Term::Phrase(_));
+				let occur = match occur {
+					Some(occur) => occur,
+					None if pending_and => Occur::Must,
+					None => Occur::Should,
+				};
+				pending_and = false;
+				clauses.push(Clause { occur, field, term });
+			}
+		}
+	}
+
+	// normalize and stem the words of the whole query like those of multi-word keywords
+	let whole = stemmer.stem(&tokenize::words(query).join(" ")).into_owned();
+	if !has_syntax && !whole.is_empty() {
+		groups[0].push(Clause {
+			occur: Occur::Should,
+			field: None,
+			term: Term::Word(whole),
+		});
+	}
+
+	let groups = groups
+		.into_iter()
+		.filter(|clauses| !clauses.is_empty())
+		.map(|clauses| {
+			let mut unique: Vec<Clause> = Vec::with_capacity(clauses.len());
+			for clause in clauses {
+				if !unique.contains(&clause) {
+					unique.push(clause);
+				}
+			}
+			unique
+		})
+		.collect();
+	Query { groups }
+}
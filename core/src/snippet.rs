@@ -0,0 +1,131 @@
+use crate::{Snippet, SnippetOptions, Stemmer, tokenize};
+use std::collections::HashSet;
+use std::ops::Range;
+
+const ELLIPSIS: &str = "…";
+
+fn escape_html(text: &str, out: &mut String) {
+	for c in text.chars() {
+		match c {
+			'&' => out.push_str("&amp;"),
+			'<' => out.push_str("&lt;"),
+			'>' => out.push_str("&gt;"),
+			'"' => out.push_str("&quot;"),
+			'\'' => out.push_str("&#39;"),
+			c => out.push(c),
+		}
+	}
+}
+
+/// Cut a window of about `options.length` characters out of `body`, around
+/// the area with the most words of the matched `keywords`, which were stemmed
+/// with `stemmer`.
+pub(crate) fn build_snippet(
+	body: &str,
+	keywords: &[&str],
+	stemmer: Stemmer,
+	options: &SnippetOptions,
+) -> Snippet {
+	let keyword_words: HashSet<&str> = keywords
+		.iter()
+		.flat_map(|keyword| keyword.split_whitespace())
+		.collect();
+	let mut matches: Vec<Range<usize>> = Vec::new();
+	let is_keyword = |word: &str| keyword_words.contains(stemmer.stem(word).as_ref());
+	for (range, word) in tokenize::tokens(body) {
+		// identifiers also match by their parts, e.g. `max_retry_count` for `retry`
+		let matched = is_keyword(&word)
+			|| tokenize::identifier(&body[range.clone()])
+				.is_some_and(|(_, parts)| parts.iter().any(|part| is_keyword(part)));
+		if !matched {
+			continue;
+		}
+		// CJK bigrams overlap, highlight them as one
+		match matches.last_mut() {
+			Some(last) if range.start < last.end => last.end = last.end.max(range.end),
+			_ => matches.push(range),
+		}
+	}
+
+	// byte offset of every character, plus the end of the body
+	let boundaries: Vec<usize> = body
+		.char_indices()
+		.map(|(i, _)| i)
+		.chain(std::iter::once(body.len()))
+		.collect();
+	let char_count = boundaries.len() - 1;
+	let char_at = |byte: usize| boundaries.partition_point(|b| *b < byte);
+
+	// pick the match followed by the most other matches within the window
+	let anchor = (0..matches.len())
+		.max_by_key(|&i| {
+			let limit = char_at(matches[i].start) + options.length;
+			let count = matches[i..]
+				.iter()
+				.take_while(|m| char_at(m.end) <= limit)
+				.count();
+			(count, std::cmp::Reverse(i))
+		})
+		.map(|i| matches[i].clone());
+
+	let (mut start, mut end) = match &anchor {
+		Some(anchor) => {
+			let start = char_at(anchor.start).saturating_sub(options.length / 4);
+			let end = (start + options.length).min(char_count);
+			(end.saturating_sub(options.length), end)
+		}
+		None => (0, options.length.min(char_count)),
+	};
+
+	// avoid cutting words in half, without moving past the anchor
+	let is_space = |i: usize| body[boundaries[i]..].starts_with(char::is_whitespace);
+	if start > 0 && !is_space(start - 1) {
+		let limit = anchor.as_ref().map_or(end, |a| char_at(a.start));
+		if let Some(space) = (start..limit).find(|&i| is_space(i)) {
+			start = space + 1;
+		}
+	}
+	if end < char_count && !is_space(end) {
+		let limit = anchor.as_ref().map_or(start, |a| char_at(a.end));
+		if let Some(space) = (limit..end).rev().find(|&i| is_space(i)) {
+			end = space;
+		}
+	}
+
+	let window = boundaries[start]..boundaries[end];
+	let prefix = if start > 0 { ELLIPSIS } else { "" };
+	let suffix = if end < char_count { ELLIPSIS } else { "" };
+	let text = body[window.clone()].trim();
+	let offset = window.start + (body[window.clone()].len() - body[window].trim_start().len());
+	let highlights = matches
+		.into_iter()
+		.filter(|m| m.start >= offset && m.end <= offset + text.len())
+		.map(|m| m.start - offset..m.end - offset);
+
+	if options.mark {
+		let mut marked = String::from(prefix);
+		let mut last = 0;
+		for highlight in highlights {
+			escape_html(&text[last..highlight.start], &mut marked);
+			marked.push_str("<mark>");
+			escape_html(&text[highlight.clone()], &mut marked);
+			marked.push_str("</mark>");
+			last = highlight.end;
+		}
+		escape_html(&text[last..], &mut marked);
+		marked.push_str(suffix);
+
+		Snippet {
+			text: marked,
+			highlights: Vec::new(),
+		}
+	} else {
+		let text = format!("{}{}{}", prefix, text, suffix);
+		// JavaScript indexes strings in UTF-16 code units
+		let utf16 = |byte: usize| text[..byte].encode_utf16().count();
+		let highlights = highlights
+			.map(|h| utf16(h.start + prefix.len())..utf16(h.end + prefix.len()))
+			.collect();
+		Snippet { text, highlights }
+	}
+}
@@ -0,0 +1,249 @@
+//! The Porter stemming algorithm, as described in M.F. Porter, "An algorithm for
+//! suffix stripping", 1980, including the departures of the reference
+//! implementation (`bli` and `logi` in step 2).
+
+/// A word being stemmed, `b[..=k]` is the current stem.
+struct Porter {
+	b: Vec<u8>,
+	/// End of the stem that the last matched suffix follows
+	j: isize,
+}
+
+impl Porter {
+	fn k(&self) -> isize {
+		self.b.len() as isize - 1
+	}
+
+	fn at(&self, i: isize) -> u8 {
+		self.b[i as usize]
+	}
+
+	/// Whether the letter at `i` is a consonant.
+	fn cons(&self, i: isize) -> bool {
+		match self.at(i) {
+			b'a' | b'e' | b'i' | b'o' | b'u' => false,
+			b'y' => i == 0 || !self.cons(i - 1),
+			_ => true,
+		}
+	}
+
+	/// Number of vowel-consonant sequences in `b[..=j]`.
+	fn m(&self) -> usize {
+		let mut n = 0;
+		let mut i = 0;
+		loop {
+			if i > self.j {
+				return n;
+			}
+			if !self.cons(i) {
+				break;
+			}
+			i += 1;
+		}
+		i += 1;
+		loop {
+			loop {
+				if i > self.j {
+					return n;
+				}
+				if self.cons(i) {
+					break;
+				}
+				i += 1;
+			}
+			i += 1;
+			n += 1;
+			loop {
+				if i > self.j {
+					return n;
+				}
+				if !self.cons(i) {
+					break;
+				}
+				i += 1;
+			}
+			i += 1;
+		}
+	}
+
+	/// Whether `b[..=j]` contains a vowel.
+	fn vowel_in_stem(&self) -> bool {
+		(0..=self.j).any(|i| !self.cons(i))
+	}
+
+	/// Whether `b[i - 1..=i]` is a double consonant.
+	fn double_cons(&self, i: isize) -> bool {
+		i >= 1 && self.at(i) == self.at(i - 1) && self.cons(i)
+	}
+
+	/// Whether `b[i - 2..=i]` is consonant-vowel-consonant, where the last
+	/// consonant is not w, x or y.
+	fn cvc(&self, i: isize) -> bool {
+		i >= 2
+			&& self.cons(i)
+			&& !self.cons(i - 1)
+			&& self.cons(i - 2)
+			&& !matches!(self.at(i), b'w' | b'x' | b'y')
+	}
+
+	/// Whether the word ends with `s`, setting `j` to the end of the stem before it.
+	fn ends(&mut self, s: &str) -> bool {
+		if !self.b.ends_with(s.as_bytes()) {
+			return false;
+		}
+		self.j = self.k() - s.len() as isize;
+		true
+	}
+
+	/// Replace the suffix after `j` with `s`.
+	fn set_to(&mut self, s: &str) {
+		self.b.truncate((self.j + 1) as usize);
+		self.b.extend_from_slice(s.as_bytes());
+	}
+
+	fn replace_if_measured(&mut self, s: &str) {
+		if self.m() > 0 {
+			self.set_to(s);
+		}
+	}
+
+	/// Remove plurals and -ed or -ing.
+	fn step1ab(&mut self) {
+		if self.at(self.k()) == b's' {
+			if self.ends("sses") {
+				self.b.truncate(self.b.len() - 2);
+			} else if self.ends("ies") {
+				self.set_to("i");
+			} else if self.at(self.k() - 1) != b's' {
+				self.b.pop();
+			}
+		}
+		if self.ends("eed") {
+			if self.m() > 0 {
+				self.b.pop();
+			}
+		} else if (self.ends("ed") || self.ends("ing")) && self.vowel_in_stem() {
+			self.b.truncate((self.j + 1) as usize);
+			if self.ends("at") {
+				self.set_to("ate");
+			} else if self.ends("bl") {
+				self.set_to("ble");
+			} else if self.ends("iz") {
+				self.set_to("ize");
+			} else if self.double_cons(self.k()) {
+				if !matches!(self.at(self.k()), b'l' | b's' | b'z') {
+					self.b.pop();
+				}
+			} else if self.m() == 1 && self.cvc(self.k()) {
+				self.set_to("e");
+			}
+		}
+	}
+
+	/// Turn a terminal y into i when there is another vowel in the stem.
+	fn step1c(&mut self) {
+		if self.ends("y") && self.vowel_in_stem() {
+			let k = self.k() as usize;
+			self.b[k] = b'i';
+		}
+	}
+
+	/// Map double suffixes to single ones, e.g. -ization to -ize.
+	fn step2(&mut self) {
+		const SUFFIXES: &[(&str, &str)] = &[
+			("ational", "ate"),
+			("tional", "tion"),
+			("enci", "ence"),
+			("anci", "ance"),
+			("izer", "ize"),
+			("bli", "ble"),
+			("alli", "al"),
+			("entli", "ent"),
+			("eli", "e"),
+			("ousli", "ous"),
+			("ization", "ize"),
+			("ation", "ate"),
+			("ator", "ate"),
+			("alism", "al"),
+			("iveness", "ive"),
+			("fulness", "ful"),
+			("ousness", "ous"),
+			("aliti", "al"),
+			("iviti", "ive"),
+			("biliti", "ble"),
+			("logi", "log"),
+		];
+		self.replace_first(SUFFIXES);
+	}
+
+	/// Handle -ic-, -full, -ness and the like.
+	fn step3(&mut self) {
+		const SUFFIXES: &[(&str, &str)] = &[
+			("icate", "ic"),
+			("ative", ""),
+			("alize", "al"),
+			("iciti", "ic"),
+			("ical", "ic"),
+			("ful", ""),
+			("ness", ""),
+		];
+		self.replace_first(SUFFIXES);
+	}
+
+	fn replace_first(&mut self, suffixes: &[(&str, &str)]) {
+		if let Some((_, replacement)) = suffixes.iter().find(|(suffix, _)| self.ends(suffix)) {
+			self.replace_if_measured(replacement);
+		}
+	}
+
+	/// Remove -ant, -ence and the like when the stem is long enough.
+	fn step4(&mut self) {
+		const SUFFIXES: &[&str] = &[
+			"al", "ance", "ence", "er", "ic", "able", "ible", "ant", "ement", "ment", "ent", "ion", "ou",
+			"ism", "ate", "iti", "ous", "ive", "ize",
+		];
+		let found = SUFFIXES.iter().any(|suffix| {
+			self.ends(suffix)
+				&& (*suffix != "ion" || (self.j >= 0 && matches!(self.at(self.j), b's' | b't')))
+		});
+		if found && self.m() > 1 {
+			self.b.truncate((self.j + 1) as usize);
+		}
+	}
+
+	/// Remove a final -e and reduce -ll when the stem is long enough.
+	fn step5(&mut self) {
+		self.j = self.k();
+		if self.at(self.k()) == b'e' {
+			let m = self.m();
+			if m > 1 || (m == 1 && !self.cvc(self.k() - 1)) {
+				self.b.pop();
+			}
+		}
+		if self.at(self.k()) == b'l' && self.double_cons(self.k()) && self.m() > 1 {
+			self.b.pop();
+		}
+	}
+}
+
+/// Stem a lowercase English word. Words that are short or not plain ASCII
+/// letters are returned unchanged.
+pub(crate) fn porter(word: &str) -> String {
+	if word.len() <= 2 || !word.bytes().all(|b| b.is_ascii_lowercase()) {
+		return word.to_string();
+	}
+
+	let mut porter = Porter {
+		b: word.as_bytes().to_vec(),
+		j: 0,
+	};
+	porter.step1ab();
+	if porter.k() > 0 {
+		porter.step1c();
+		porter.step2();
+		porter.step3();
+		porter.step4();
+		porter.step5();
+	}
+	String::from_utf8(porter.b).expect("ASCII input")
+}
@@ -1,7 +1,37 @@
 mod tests {
 	use crate::Index;
-	use crate::{Document, FsstStrVec};
-	use crate::{build_index, search};
+	use crate::query::{Clause, Occur, Term, parse};
+	use crate::{Bm25Parameters, FieldBoosts, Ranking, Stemmer, TypoTolerance, edit_distance};
+	use crate::{
+		CategoryFacet, ResultMode, SearchOptions, SnippetOptions, build_index, get_document, search,
+		search_with_options,
+	};
+	use crate::{Document, Field, FsstStrVec, FsstStrView, IndexView, MatchType, Posting};
+	use crate::{IndexOptions, KeywordWeights, build_index_with_options};
+	use crate::{encode_postings, read_u32};
+	use std::borrow::Cow;
+	use std::collections::BTreeMap;
+
+	/// A document with only a title, href and body, the other fields are set with
+	/// struct update syntax, e.g. `Document { category, ..doc(title, href, body) }`.
+	fn doc(title: &str, href: &str, body: &str) -> Document {
+		Document {
+			title: title.to_string(),
+			href: href.to_string(),
+			body: body.to_string(),
+			..Default::default()
+		}
+	}
+
+	/// The titles of the documents found for `query`, best first.
+	fn titles(view: &IndexView, query: &str, options: &SearchOptions) -> Vec<String> {
+		search_with_options(view, query, options)
+			.unwrap()
+			.hits
+			.into_iter()
+			.map(|hit| hit.document.unwrap().title)
+			.collect()
+	}
 
 	// ========================================================================
 	// SECTION 1: Basic Sanity Tests - FsstStrVec
@@ -81,6 +111,93 @@ mod tests {
 		assert_eq!(vec.get(2), Some("Café ☕".to_string()));
 	}
 
+	#[test]
+	fn test_fsst_str_vec_decodes_symbols_once() {
+		let strings: Vec<String> = (0..100)
+			.map(|i| format!("Document {} about the editor", i))
+			.collect();
+		let vec = FsstStrVec::from_strings(&strings);
+		assert!(vec.symbols.get().is_none());
+
+		assert_eq!(vec.get(0).as_ref(), Some(&strings[0]));
+		let symbols = vec.symbols.get().unwrap().as_ptr();
+		for _ in 0..100 {
+			for (i, string) in strings.iter().enumerate() {
+				assert_eq!(vec.get(i).as_ref(), Some(string));
+			}
+		}
+		// every lookup reused the symbols decoded by the first one
+		assert_eq!(vec.symbols.get().unwrap().as_ptr(), symbols);
+	}
+
+	#[test]
+	#[ignore = "timing benchmark, run with `cargo test -- --ignored`"]
+	fn test_fsst_str_vec_cached_decompressor_benchmark() {
+		// Compare repeated lookups against rebuilding the decompressor on every
+		// call, which is what `get` used to do
+		let strings: Vec<String> = (0..1000)
+			.map(|i| {
+				format!(
+					"Document {} about configuring the editor and its extensions",
+					i
+				)
+			})
+			.collect();
+		let vec = FsstStrVec::from_strings(&strings);
+		let lookups = 20_000;
+
+		let start = std::time::Instant::now();
+		for i in 0..lookups {
+			let s = vec.get(i % strings.len()).unwrap();
+			assert_eq!(s.len(), strings[i % strings.len()].len());
+		}
+		let cached = start.elapsed();
+
+		let start = std::time::Instant::now();
+		for i in 0..lookups {
+			let i = i % strings.len();
+			let syms: Vec<fsst::Symbol> = vec
+				.dict_syms
+				.chunks_exact(8)
+				.map(|sym| fsst::Symbol::from_slice(sym.try_into().unwrap()))
+				.collect();
+			let decomp = fsst::Decompressor::new(&syms, &vec.dict_lens);
+			let start = read_u32(&vec.offsets, i) as usize;
+			let end = if i + 1 < vec.len() {
+				read_u32(&vec.offsets, i + 1) as usize
+			} else {
+				vec.data.len()
+			};
+			let bytes = decomp.decompress(&vec.data[start..end]);
+			assert_eq!(bytes.len(), strings[i].len());
+		}
+		let rebuilt = start.elapsed();
+
+		println!(
+			"{} lookups: cached {:?}, rebuilt {:?}",
+			lookups, cached, rebuilt
+		);
+		assert!(cached < rebuilt);
+	}
+
+	#[test]
+	fn test_fsst_str_vec_roundtrip_through_view() {
+		// Test that views share the cached symbols and borrow from serialized bytes
+		let strings = vec!["hello", "world"];
+		let vec = FsstStrVec::from_strings(&strings);
+		assert!(vec.symbols.get().is_none());
+		assert_eq!(vec.get(1), Some("world".to_string()));
+		assert!(matches!(vec.view().symbols, Cow::Borrowed(_)));
+
+		let bytes = postcard::to_allocvec(&vec.view()).unwrap();
+		let loaded: FsstStrView = postcard::from_bytes(&bytes).unwrap();
+		assert!(bytes.as_ptr_range().contains(&loaded.data.as_ptr()));
+		assert_eq!(loaded.len(), 2);
+		assert_eq!(loaded.get(0), Some("hello".to_string()));
+		assert_eq!(loaded.get(1), Some("world".to_string()));
+		assert_eq!(loaded.get(2), None);
+	}
+
 	// ========================================================================
 	// SECTION 2: Document Structure Tests
 	// ========================================================================
@@ -94,6 +211,9 @@ mod tests {
 			href: "/test".to_string(),
 			body: "This is a test document body".to_string(),
 			keywords: Some(vec!["test".to_string(), "document".to_string()]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
 		};
 
 		assert_eq!(doc.title, "Test Document");
@@ -115,6 +235,9 @@ mod tests {
 			href: "/link".to_string(),
 			body: "Body text".to_string(),
 			keywords: Some(vec!["test".to_string(), "example".to_string()]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
 		};
 
 		let serialized = serde_json::to_string(&doc).unwrap();
@@ -142,6 +265,9 @@ mod tests {
 				href: "/docs/rust".to_string(),
 				body: "Learn Rust programming language".to_string(),
 				keywords: Some(vec!["rust".to_string(), "programming".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Python Guide".to_string(),
@@ -149,6 +275,9 @@ mod tests {
 				href: "/docs/python".to_string(),
 				body: "Python is a versatile programming language".to_string(),
 				keywords: Some(vec!["python".to_string(), "guide".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
@@ -156,7 +285,10 @@ mod tests {
 		assert!(index.is_ok());
 
 		let index = index.unwrap();
-		assert_eq!(index.document_strings.len(), 8); // 4 strings per document * 2 documents
+		assert_eq!(index.document_strings.len(), 6); // 3 strings per document * 2 documents
+		assert_eq!(index.categories, vec!["Documentation".to_string()]);
+		assert_eq!(index.view().document_count(), 2);
+		assert_eq!(index.view().document_category(1), 0);
 	}
 
 	#[test]
@@ -179,13 +311,16 @@ mod tests {
 			href: "/single".to_string(),
 			body: "This is the only document".to_string(),
 			keywords: Some(vec!["single".to_string(), "document".to_string()]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
 		}];
 
 		let index = build_index(documents);
 		assert!(index.is_ok());
 
 		let index = index.unwrap();
-		assert_eq!(index.document_strings.len(), 4); // title, category, href, body
+		assert_eq!(index.document_strings.len(), 3); // title, href, body
 	}
 
 	#[test]
@@ -198,6 +333,9 @@ mod tests {
 				href: "/guide1".to_string(),
 				body: "First guide".to_string(),
 				keywords: Some(vec!["getting".to_string(), "started".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Getting Started".to_string(),
@@ -205,6 +343,9 @@ mod tests {
 				href: "/tutorial1".to_string(),
 				body: "First tutorial".to_string(),
 				keywords: Some(vec!["getting".to_string(), "started".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
@@ -226,6 +367,9 @@ mod tests {
 			href: "/test".to_string(),
 			body: "This is a test document".to_string(),
 			keywords: Some(vec!["test".to_string(), "document".to_string()]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
 		}];
 
 		let index = build_index(documents).unwrap();
@@ -255,6 +399,9 @@ mod tests {
 				href: "/doc1".to_string(),
 				body: "Content for document one".to_string(),
 				keywords: Some(vec!["document".to_string(), "one".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Document Two".to_string(),
@@ -262,6 +409,9 @@ mod tests {
 				href: "/doc2".to_string(),
 				body: "Content for document two".to_string(),
 				keywords: Some(vec!["document".to_string(), "two".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
@@ -296,6 +446,9 @@ mod tests {
 				href: "/docs/rust".to_string(),
 				body: "Learn Rust programming language".to_string(),
 				keywords: Some(vec!["rust".to_string(), "programming".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Python Guide".to_string(),
@@ -303,15 +456,21 @@ mod tests {
 				href: "/docs/python".to_string(),
 				body: "Python is a versatile programming language".to_string(),
 				keywords: Some(vec!["python".to_string(), "guide".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "Rust", 10).unwrap();
+		let results = search(&index.view(), "Rust", 10).unwrap();
 
 		assert!(!results.is_empty());
-		assert_eq!(results[0].title, "Rust Programming");
-		assert_eq!(results[0].href, "/docs/rust");
+		assert_eq!(
+			results[0].document.as_ref().unwrap().title,
+			"Rust Programming"
+		);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/docs/rust");
 	}
 
 	#[test]
@@ -323,22 +482,34 @@ mod tests {
 			href: "/tutorials/javascript".to_string(),
 			body: "Learn JavaScript programming".to_string(),
 			keywords: Some(vec!["javascript".to_string(), "tutorial".to_string()]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
 		}];
 
 		let index = build_index(documents).unwrap();
 
-		let results_lower = search(&index, "javascript", 10).unwrap();
-		let results_upper = search(&index, "JAVASCRIPT", 10).unwrap();
-		let results_mixed = search(&index, "JavaScript", 10).unwrap();
+		let results_lower = search(&index.view(), "javascript", 10).unwrap();
+		let results_upper = search(&index.view(), "JAVASCRIPT", 10).unwrap();
+		let results_mixed = search(&index.view(), "JavaScript", 10).unwrap();
 
 		assert!(!results_lower.is_empty());
 		assert!(!results_upper.is_empty());
 		assert!(!results_mixed.is_empty());
 
 		// All should find the same document
-		assert_eq!(results_lower[0].href, "/tutorials/javascript");
-		assert_eq!(results_upper[0].href, "/tutorials/javascript");
-		assert_eq!(results_mixed[0].href, "/tutorials/javascript");
+		assert_eq!(
+			results_lower[0].document.as_ref().unwrap().href,
+			"/tutorials/javascript"
+		);
+		assert_eq!(
+			results_upper[0].document.as_ref().unwrap().href,
+			"/tutorials/javascript"
+		);
+		assert_eq!(
+			results_mixed[0].document.as_ref().unwrap().href,
+			"/tutorials/javascript"
+		);
 	}
 
 	#[test]
@@ -350,10 +521,13 @@ mod tests {
 			href: "/docs/rust".to_string(),
 			body: "Learn Rust programming language".to_string(),
 			keywords: Some(vec!["rust".to_string(), "programming".to_string()]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
 		}];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "NonexistentKeyword", 10).unwrap();
+		let results = search(&index.view(), "NonexistentKeyword", 10).unwrap();
 
 		assert!(results.is_empty());
 	}
@@ -367,10 +541,13 @@ mod tests {
 			href: "/test".to_string(),
 			body: "Test content".to_string(),
 			keywords: Some(vec!["test".to_string(), "document".to_string()]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
 		}];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "", 10).unwrap();
+		let results = search(&index.view(), "", 10).unwrap();
 
 		// Empty query should return no results (or possibly all results depending on implementation)
 		// Just verify it doesn't crash
@@ -395,6 +572,9 @@ mod tests {
 					"code".to_string(),
 					"extensions".to_string(),
 				]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "VS Code Settings".to_string(),
@@ -406,6 +586,9 @@ mod tests {
 					"code".to_string(),
 					"settings".to_string(),
 				]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Python Guide".to_string(),
@@ -413,16 +596,27 @@ mod tests {
 				href: "/docs/python".to_string(),
 				body: "Python is a versatile programming language".to_string(),
 				keywords: Some(vec!["python".to_string(), "guide".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "VS Code", 10).unwrap();
+		let results = search(&index.view(), "VS Code", 10).unwrap();
 
 		// Should find both VS Code documents
 		assert!(results.len() >= 2);
-		assert!(results.iter().any(|d| d.href == "/docs/extensions"));
-		assert!(results.iter().any(|d| d.href == "/docs/settings"));
+		assert!(
+			results
+				.iter()
+				.any(|d| d.document.as_ref().unwrap().href == "/docs/extensions")
+		);
+		assert!(
+			results
+				.iter()
+				.any(|d| d.document.as_ref().unwrap().href == "/docs/settings")
+		);
 	}
 
 	#[test]
@@ -438,10 +632,13 @@ mod tests {
 				"vs".to_string(),
 				"code".to_string(),
 			]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
 		}];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "debug", 10).unwrap();
+		let results = search(&index.view(), "debug", 10).unwrap();
 
 		// Should find documents with "debugging" and "debug"
 		assert!(!results.is_empty());
@@ -461,6 +658,9 @@ mod tests {
 				href: "/tutorials/python".to_string(),
 				body: "Learn programming with this tutorial".to_string(),
 				keywords: Some(vec!["python".to_string(), "tutorial".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Getting Started".to_string(),
@@ -468,15 +668,21 @@ mod tests {
 				href: "/docs/start".to_string(),
 				body: "This guide covers Python basics and advanced features".to_string(),
 				keywords: Some(vec!["getting".to_string(), "started".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "Python", 10).unwrap();
+		let results = search(&index.view(), "Python", 10).unwrap();
 
 		// Document with "Python" in title should rank first
 		assert!(!results.is_empty());
-		assert_eq!(results[0].href, "/tutorials/python");
+		assert_eq!(
+			results[0].document.as_ref().unwrap().href,
+			"/tutorials/python"
+		);
 	}
 
 	#[test]
@@ -493,6 +699,9 @@ mod tests {
 					"code".to_string(),
 					"debugging".to_string(),
 				]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "VS Code Overview".to_string(),
@@ -504,6 +713,9 @@ mod tests {
 					"code".to_string(),
 					"overview".to_string(),
 				]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Debugging Guide".to_string(),
@@ -511,15 +723,21 @@ mod tests {
 				href: "/tutorials/debug".to_string(),
 				body: "General debugging techniques".to_string(),
 				keywords: Some(vec!["debugging".to_string(), "guide".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "VS Code debugging", 10).unwrap();
+		let results = search(&index.view(), "VS Code debugging", 10).unwrap();
 
 		// Document with all three keywords should rank first
 		assert!(!results.is_empty());
-		assert_eq!(results[0].href, "/docs/debugging");
+		assert_eq!(
+			results[0].document.as_ref().unwrap().href,
+			"/docs/debugging"
+		);
 	}
 
 	#[test]
@@ -532,6 +750,9 @@ mod tests {
 				href: "/guide1".to_string(),
 				body: "First guide about programming".to_string(),
 				keywords: Some(vec!["guide".to_string(), "one".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Guide Two".to_string(),
@@ -539,6 +760,9 @@ mod tests {
 				href: "/guide2".to_string(),
 				body: "Second guide about programming".to_string(),
 				keywords: Some(vec!["guide".to_string(), "two".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Guide Three".to_string(),
@@ -546,6 +770,9 @@ mod tests {
 				href: "/guide3".to_string(),
 				body: "Third guide about programming".to_string(),
 				keywords: Some(vec!["guide".to_string(), "three".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Guide Four".to_string(),
@@ -553,14 +780,17 @@ mod tests {
 				href: "/guide4".to_string(),
 				body: "Fourth guide about programming".to_string(),
 				keywords: Some(vec!["guide".to_string(), "four".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
 		let index = build_index(documents).unwrap();
 
-		let results_2 = search(&index, "guide", 2).unwrap();
-		let results_3 = search(&index, "guide", 3).unwrap();
-		let results_10 = search(&index, "guide", 10).unwrap();
+		let results_2 = search(&index.view(), "guide", 2).unwrap();
+		let results_3 = search(&index.view(), "guide", 3).unwrap();
+		let results_10 = search(&index.view(), "guide", 10).unwrap();
 
 		assert!(results_2.len() <= 2);
 		assert!(results_3.len() <= 3);
@@ -581,6 +811,9 @@ mod tests {
 				href: "/docs/typescript".to_string(),
 				body: "Configure TypeScript with tsconfig.json for your project".to_string(),
 				keywords: Some(vec!["typescript".to_string(), "configuration".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "JavaScript Basics".to_string(),
@@ -588,6 +821,9 @@ mod tests {
 				href: "/tutorials/javascript".to_string(),
 				body: "Learn JavaScript fundamentals".to_string(),
 				keywords: Some(vec!["javascript".to_string(), "basics".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Language Support".to_string(),
@@ -595,14 +831,21 @@ mod tests {
 				href: "/docs/languages".to_string(),
 				body: "VS Code supports TypeScript, JavaScript, and many other languages".to_string(),
 				keywords: Some(vec!["language".to_string(), "support".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "TypeScript", 10).unwrap();
+		let results = search(&index.view(), "TypeScript", 10).unwrap();
 
 		assert!(!results.is_empty());
-		assert!(results.iter().any(|d| d.href == "/docs/typescript"));
+		assert!(
+			results
+				.iter()
+				.any(|d| d.document.as_ref().unwrap().href == "/docs/typescript")
+		);
 	}
 
 	#[test]
@@ -615,6 +858,9 @@ mod tests {
 				href: "/docs/cpp".to_string(),
 				body: "Learn C++ programming language".to_string(),
 				keywords: Some(vec!["c++".to_string(), "programming".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "C# Guide".to_string(),
@@ -622,12 +868,15 @@ mod tests {
 				href: "/docs/csharp".to_string(),
 				body: "C# development with .NET".to_string(),
 				keywords: Some(vec!["c#".to_string(), "guide".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
 		let index = build_index(documents).unwrap();
-		let results_cpp = search(&index, "C++", 10);
-		let results_csharp = search(&index, "C#", 10);
+		let results_cpp = search(&index.view(), "C++", 10);
+		let results_csharp = search(&index.view(), "C#", 10);
 
 		// Should handle special characters gracefully
 		assert!(results_cpp.is_ok() as bool);
@@ -648,6 +897,9 @@ mod tests {
 					"development".to_string(),
 					"setup".to_string(),
 				]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Development Environment".to_string(),
@@ -655,6 +907,9 @@ mod tests {
 				href: "/docs/environment".to_string(),
 				body: "Configure your local development environment".to_string(),
 				keywords: Some(vec!["development".to_string(), "environment".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Remote Connections".to_string(),
@@ -662,15 +917,21 @@ mod tests {
 				href: "/docs/remote".to_string(),
 				body: "Connect to remote servers and containers".to_string(),
 				keywords: Some(vec!["remote".to_string(), "connections".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "remote development", 10).unwrap();
+		let results = search(&index.view(), "remote development", 10).unwrap();
 
 		// Should find the document that has both keywords together
 		assert!(!results.is_empty());
-		assert_eq!(results[0].href, "/tutorials/remote-dev");
+		assert_eq!(
+			results[0].document.as_ref().unwrap().href,
+			"/tutorials/remote-dev"
+		);
 	}
 
 	#[test]
@@ -687,10 +948,13 @@ mod tests {
 				"vs".to_string(),
 				"code".to_string(),
 			]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
 		}];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "getting started with vscode", 10).unwrap();
+		let results = search(&index.view(), "getting started with vscode", 10).unwrap();
 
 		// Should find results despite stop words like "with", "the", "a"
 		assert!(!results.is_empty());
@@ -710,6 +974,9 @@ mod tests {
 					"18".to_string(),
 					"features".to_string(),
 				]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Node.js 16 Support".to_string(),
@@ -721,15 +988,22 @@ mod tests {
 					"16".to_string(),
 					"support".to_string(),
 				]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "nodejs 18", 10).unwrap();
+		let results = search(&index.view(), "nodejs 18", 10).unwrap();
 
 		assert!(!results.is_empty());
 		// Should find the Node.js 18 document
-		assert!(results.iter().any(|d| d.href.contains("nodejs18")));
+		assert!(
+			results
+				.iter()
+				.any(|d| d.document.as_ref().unwrap().href.contains("nodejs18"))
+		);
 	}
 
 	#[test]
@@ -747,6 +1021,9 @@ mod tests {
 					"ssh".to_string(),
 					"extension".to_string(),
 				]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "SSH Key Setup".to_string(),
@@ -758,11 +1035,19 @@ mod tests {
 					"key".to_string(),
 					"setup".to_string(),
 				]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "how do i connect to a remote server using ssh", 10).unwrap();
+		let results = search(
+			&index.view(),
+			"how do i connect to a remote server using ssh",
+			10,
+		)
+		.unwrap();
 
 		// Should extract relevant keywords and find documents
 		assert!(!results.is_empty());
@@ -783,6 +1068,9 @@ mod tests {
 				href: format!("/doc{}", i).to_string(),
 				body: format!("This is document number {} with some content", i).to_string(),
 				keywords: Some(vec![format!("document{}", i).to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			});
 		}
 
@@ -793,13 +1081,16 @@ mod tests {
 			href: "/special".to_string(),
 			body: "This document should be easy to find".to_string(),
 			keywords: Some(vec!["special".to_string(), "target".to_string()]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
 		});
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "special target", 10).unwrap();
+		let results = search(&index.view(), "special target", 10).unwrap();
 
 		assert!(!results.is_empty());
-		assert_eq!(results[0].href, "/special");
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/special");
 	}
 
 	#[test]
@@ -812,6 +1103,9 @@ mod tests {
 				href: "/empty1".to_string(),
 				body: "This document has no title".to_string(),
 				keywords: Some(vec!["empty".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 			Document {
 				title: "Empty Body".to_string(),
@@ -819,13 +1113,16 @@ mod tests {
 				href: "/empty2".to_string(),
 				body: "".to_string(),
 				keywords: Some(vec!["empty".to_string(), "body".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
 			},
 		];
 
 		let index = build_index(documents);
 		assert!(index.is_ok());
 
-		let results = search(&index.unwrap(), "empty", 10).unwrap();
+		let results = search(&index.unwrap().view(), "empty", 10).unwrap();
 		// Should handle empty fields gracefully
 		assert!(!results.is_empty());
 	}
@@ -839,36 +1136,43 @@ mod tests {
 			href: "/whitespace".to_string(),
 			body: "Multiple   spaces   between   words".to_string(),
 			keywords: Some(vec!["whitespace".to_string(), "test".to_string()]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
 		}];
 
 		let index = build_index(documents).unwrap();
-		let results = search(&index, "  whitespace  test  ", 10).unwrap();
+		let results = search(&index.view(), "  whitespace  test  ", 10).unwrap();
 
 		assert!(!results.is_empty());
 	}
 
 	#[test]
 	fn test_search_with_typo() -> Result<(), Box<dyn std::error::Error>> {
-		let document_strings = FsstStrVec::from_strings(&vec![
+		let document_strings = FsstStrVec::from_strings(&[
 			"Document 1",
-			"Docs",
 			"/doc1",
 			"This is the first document.",
 			"Document 2",
-			"Docs",
 			"/doc2",
 			"This is the second document.",
 			"Document 3",
-			"Docs",
 			"/doc3",
 			"This is the third document.",
 		]);
 
-		let keyword_to_documents: Vec<Vec<(usize, u8)>> = vec![
-			vec![(1, 1)],          // "language" appears in doc 1
-			vec![(0, 10), (2, 4)], // "programming" appears in doc 0 and 2
-			vec![(0, 5), (1, 3)],  // "rust" appears in doc 0 and 1
-		];
+		let posting = |document: usize, score: u16| Posting {
+			document,
+			score,
+			fields: Field::Body.bit(),
+			origin: Field::Body,
+			term_frequency: 1,
+		};
+		let (posting_offsets, postings) = encode_postings(&[
+			vec![posting(1, 1)],                 // "language" appears in doc 1
+			vec![posting(0, 10), posting(2, 4)], // "programming" appears in doc 0 and 2
+			vec![posting(0, 5), posting(1, 3)],  // "rust" appears in doc 0 and 1
+		]);
 
 		let mut fst_builder = fst::MapBuilder::memory();
 		fst_builder.insert("language", 0).unwrap();
@@ -879,12 +1183,2720 @@ mod tests {
 		let index = Index {
 			fst,
 			document_strings,
-			keyword_to_documents,
+			categories: vec!["Docs".to_string()],
+			document_categories: vec![0; 12],
+			document_lengths: vec![0; 12],
+			posting_offsets,
+			postings,
+			ranking: Ranking::Keyword,
+			bm25: Bm25Parameters::default(),
+			average_document_length: 0.0,
+			score_scale: 1.0,
+			stemmer: Stemmer::None,
+			synonyms: Vec::new(),
+			stored_fields: Vec::new(),
+			filter_fields: Vec::new(),
+			attributes: Vec::new(),
+			sort_columns: Vec::new(),
+			popularity: Vec::new(),
 		};
 
-		let results = search(&index, "lamguage", 10)?;
+		let results = search(&index.view(), "lamguage", 10)?;
 		assert_eq!(results.len(), 1, "Expected 1 result for 'lamguage'");
 
 		Ok(())
 	}
+
+	// ========================================================================
+	// SECTION 10: Search Hit Details
+	// ========================================================================
+
+	#[test]
+	fn test_search_hit_scores_are_descending() {
+		// Test that hits expose their aggregated score in ranking order
+		let documents = vec![
+			Document {
+				title: "Python Tutorial".to_string(),
+				category: "Tutorials".to_string(),
+				href: "/tutorials/python".to_string(),
+				body: "Learn programming with this tutorial".to_string(),
+				keywords: Some(vec!["python".to_string(), "tutorial".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
+			},
+			Document {
+				title: "Getting Started".to_string(),
+				category: "Documentation".to_string(),
+				href: "/docs/start".to_string(),
+				body: "This guide covers Python basics and advanced features".to_string(),
+				keywords: Some(vec!["getting".to_string(), "started".to_string()]),
+				language: None,
+				fields: BTreeMap::new(),
+				popularity: None,
+			},
+		];
+
+		let index = build_index(documents).unwrap();
+		let results = search(&index.view(), "python", 10).unwrap();
+
+		assert!(!results.is_empty());
+		assert_eq!(results[0].score, 100.0); // explicit keyword score
+		assert!(results.windows(2).all(|w| w[0].score >= w[1].score));
+	}
+
+	#[test]
+	fn test_search_hit_match_types() {
+		// Test that matched keywords are reported with how they matched
+		let documents = vec![Document {
+			title: "Debugging".to_string(),
+			category: "Documentation".to_string(),
+			href: "/docs/debugging".to_string(),
+			body: "".to_string(),
+			keywords: Some(vec!["config".to_string(), "configuration".to_string()]),
+			language: None,
+			fields: BTreeMap::new(),
+			popularity: None,
+		}];
+
+		let index = build_index(documents).unwrap();
+
+		let results = search(&index.view(), "config", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		let matches = &results[0].matches;
+		assert_eq!(matches[0].keyword, "config");
+		assert_eq!(matches[0].match_type, MatchType::Exact);
+		assert!(
+			matches
+				.iter()
+				.any(|m| m.keyword == "configuration" && m.match_type == MatchType::Prefix)
+		);
+
+		let results = search(&index.view(), "conflg", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].matches.len(), 1);
+		assert_eq!(results[0].matches[0].keyword, "config");
+		assert_eq!(results[0].matches[0].match_type, MatchType::Fuzzy);
+	}
+
+	// ========================================================================
+	// SECTION 11: Query Syntax Tests
+	// ========================================================================
+
+	fn syntax_documents() -> Vec<Document> {
+		vec![
+			Document {
+				category: "Guides".to_string(),
+				keywords: Some(vec!["config".to_string()]),
+				..doc(
+					"Getting Started",
+					"/guides/start",
+					"Install the editor and open a folder",
+				)
+			},
+			Document {
+				category: "Reference".to_string(),
+				keywords: Some(vec!["deprecated".to_string(), "config".to_string()]),
+				..doc(
+					"Legacy Config",
+					"/reference/legacy",
+					"These settings will be removed",
+				)
+			},
+			Document {
+				category: "Troubleshooting".to_string(),
+				keywords: Some(vec!["errors".to_string()]),
+				..doc(
+					"Started Getting Errors",
+					"/troubleshooting/errors",
+					"What to do when things go wrong",
+				)
+			},
+		]
+	}
+
+	#[test]
+	fn test_query_parse_syntax() {
+		let clauses = parse(
+			"\"Getting Started\" +rust -deprecated config",
+			Stemmer::None,
+		);
+		assert_eq!(
+			clauses.groups,
+			vec![vec![
+				Clause {
+					occur: Occur::Should,
+					field: None,
+					term: Term::Phrase(vec!["getting".to_string(), "started".to_string()]),
+				},
+				Clause {
+					occur: Occur::Must,
+					field: None,
+					term: Term::Word("rust".to_string()),
+				},
+				Clause {
+					occur: Occur::MustNot,
+					field: None,
+					term: Term::Word("deprecated".to_string()),
+				},
+				Clause {
+					occur: Occur::Should,
+					field: None,
+					term: Term::Word("config".to_string()),
+				},
+			]]
+		);
+	}
+
+	#[test]
+	fn test_query_parse_boolean_operators() {
+		let occurs = |query: &str| -> Vec<Vec<Occur>> {
+			parse(query, Stemmer::None)
+				.groups
+				.iter()
+				.map(|group| group.iter().map(|c| c.occur).collect())
+				.collect()
+		};
+
+		assert_eq!(occurs("a AND b"), vec![vec![Occur::Must, Occur::Must]]);
+		assert_eq!(
+			occurs("a OR b"),
+			vec![vec![Occur::Should], vec![Occur::Should]]
+		);
+		assert_eq!(
+			occurs("a b AND c"),
+			vec![vec![Occur::Should, Occur::Must, Occur::Must]]
+		);
+		assert_eq!(occurs("-a AND b"), vec![vec![Occur::MustNot, Occur::Must]]);
+		// `AND` binds tighter than `OR`
+		assert_eq!(
+			occurs("a AND b OR c"),
+			vec![vec![Occur::Must, Occur::Must], vec![Occur::Should]]
+		);
+		assert_eq!(
+			occurs("a OR b AND c"),
+			vec![vec![Occur::Should], vec![Occur::Must, Occur::Must]]
+		);
+
+		// a plain query is also matched as a whole
+		assert_eq!(
+			parse("VS Code", Stemmer::None).groups[0]
+				.last()
+				.unwrap()
+				.term,
+			Term::Word("vs code".to_string())
+		);
+		assert!(parse("", Stemmer::None).groups.is_empty());
+	}
+
+	#[test]
+	fn test_search_excluded_term() {
+		let index = build_index(syntax_documents()).unwrap();
+
+		let results = search(&index.view(), "config", 10).unwrap();
+		assert_eq!(results.len(), 2);
+
+		let results = search(&index.view(), "-deprecated config", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/guides/start");
+
+		// nothing to match when every clause is excluded
+		let results = search(&index.view(), "-deprecated", 10).unwrap();
+		assert!(results.is_empty());
+	}
+
+	#[test]
+	fn test_search_excluded_term_is_not_expanded() {
+		let document = |href: &str, keywords: &[&str]| Document {
+			category: "Docs".to_string(),
+			keywords: Some(keywords.iter().map(|k| k.to_string()).collect()),
+			..doc("Settings", href, "")
+		};
+		let documents = vec![
+			document("/apis", &["config", "apis"]),
+			document("/api", &["config", "api"]),
+			document("/reference", &["config", "api reference"]),
+			document("/versions", &["config", "apiversion"]),
+		];
+		let index = build_index(documents).unwrap();
+
+		// keywords merely sharing the prefix are kept, whole words are excluded
+		let results = search(&index.view(), "config -api", 10).unwrap();
+		let mut hrefs: Vec<String> = results
+			.into_iter()
+			.map(|hit| hit.document.unwrap().href)
+			.collect();
+		hrefs.sort();
+		assert_eq!(hrefs, vec!["/apis", "/versions"]);
+	}
+
+	#[test]
+	fn test_search_required_terms() {
+		let index = build_index(syntax_documents()).unwrap();
+
+		let results = search(&index.view(), "config legacy", 10).unwrap();
+		assert_eq!(results.len(), 2);
+
+		let results = search(&index.view(), "config +legacy", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(
+			results[0].document.as_ref().unwrap().href,
+			"/reference/legacy"
+		);
+
+		let results = search(&index.view(), "config AND errors", 10).unwrap();
+		assert!(results.is_empty());
+
+		let results = search(&index.view(), "config OR errors", 10).unwrap();
+		assert_eq!(results.len(), 3);
+
+		// either both sides of `AND`, or the other side of `OR`
+		let results = search(&index.view(), "legacy AND deprecated OR errors", 10).unwrap();
+		let mut hrefs: Vec<String> = results
+			.into_iter()
+			.map(|hit| hit.document.unwrap().href)
+			.collect();
+		hrefs.sort();
+		assert_eq!(hrefs, vec!["/reference/legacy", "/troubleshooting/errors"]);
+		let results = search(&index.view(), "errors OR legacy AND started", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(
+			results[0].document.as_ref().unwrap().href,
+			"/troubleshooting/errors"
+		);
+	}
+
+	#[test]
+	fn test_search_phrase() {
+		let index = build_index(syntax_documents()).unwrap();
+
+		let results = search(&index.view(), "getting started", 10).unwrap();
+		assert_eq!(results.len(), 2);
+
+		// the words must appear consecutively and in order
+		let results = search(&index.view(), "\"getting started\"", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/guides/start");
+	}
+
+	// ========================================================================
+	// SECTION 12: Field-Scoped Query Tests
+	// ========================================================================
+
+	fn field_documents() -> Vec<Document> {
+		vec![
+			Document {
+				category: "Guides".to_string(),
+				..doc(
+					"Install the CLI",
+					"/guides/cli",
+					"Download the binary for your platform",
+				)
+			},
+			Document {
+				category: "API Reference".to_string(),
+				..doc(
+					"Command Reference",
+					"/api/commands",
+					"Every install option and flag explained",
+				)
+			},
+		]
+	}
+
+	#[test]
+	fn test_query_parse_field_prefix() {
+		let clauses = parse(
+			"-category:\"api reference\" title:Install c++",
+			Stemmer::None,
+		)
+		.groups
+		.remove(0);
+		assert_eq!(clauses[0].occur, Occur::MustNot);
+		assert_eq!(clauses[0].field, Some(Field::Category));
+		assert_eq!(
+			clauses[0].term,
+			Term::Phrase(vec!["api".to_string(), "reference".to_string()])
+		);
+		assert_eq!(clauses[1].field, Some(Field::Title));
+		assert_eq!(clauses[1].term, Term::Word("install".to_string()));
+
+		// unknown prefixes are plain words
+		let clauses = parse("std::fs", Stemmer::None).groups.remove(0);
+		assert_eq!(clauses[0].field, None);
+		assert_eq!(clauses[0].term, Term::Word("std::fs".to_string()));
+	}
+
+	#[test]
+	fn test_search_field_scoped() {
+		let index = build_index(field_documents()).unwrap();
+
+		let results = search(&index.view(), "install", 10).unwrap();
+		assert_eq!(results.len(), 2);
+
+		let results = search(&index.view(), "title:install", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/guides/cli");
+
+		let results = search(&index.view(), "body:install", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/api/commands");
+	}
+
+	#[test]
+	fn test_search_category_and_href_fields() {
+		let index = build_index(field_documents()).unwrap();
+
+		// category and href keywords are not matched without a prefix
+		assert!(search(&index.view(), "guides", 10).unwrap().is_empty());
+
+		let results = search(&index.view(), "category:guides", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/guides/cli");
+
+		let results = search(&index.view(), "category:\"api reference\"", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/api/commands");
+
+		let results = search(&index.view(), "href:api", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/api/commands");
+
+		let results = search(&index.view(), "install -category:api", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/guides/cli");
+	}
+
+	// ========================================================================
+	// SECTION 13: Category Filter and Facet Tests
+	// ========================================================================
+
+	fn category_documents() -> Vec<Document> {
+		["Guides", "Reference", "Guides", "Tutorials"]
+			.iter()
+			.enumerate()
+			.map(|(i, category)| Document {
+				category: category.to_string(),
+				keywords: Some(vec!["extension".to_string()]),
+				..doc(
+					&format!("Extension Page {}", i),
+					&format!("/page{}", i),
+					"How to write an extension",
+				)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_search_category_filter() {
+		let index = build_index(category_documents()).unwrap();
+
+		let options = SearchOptions {
+			categories: Some(vec!["Guides".to_string()]),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "extension", &options).unwrap();
+		assert_eq!(results.hits.len(), 2);
+		assert!(
+			results
+				.hits
+				.iter()
+				.all(|h| h.document.as_ref().unwrap().category == "Guides")
+		);
+		assert!(results.facets.is_empty());
+
+		let options = SearchOptions {
+			categories: Some(vec!["Reference".to_string(), "Tutorials".to_string()]),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "extension", &options).unwrap();
+		assert_eq!(results.hits.len(), 2);
+
+		let options = SearchOptions {
+			categories: Some(vec!["Unknown".to_string()]),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "extension", &options).unwrap();
+		assert!(results.hits.is_empty());
+	}
+
+	#[test]
+	fn test_search_category_filter_before_truncation() {
+		// Test that filtering does not lose hits to the result limit
+		let index = build_index(category_documents()).unwrap();
+
+		let options = SearchOptions {
+			max_results: 1,
+			categories: Some(vec!["Tutorials".to_string()]),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "extension", &options).unwrap();
+		assert_eq!(results.hits.len(), 1);
+		assert_eq!(results.hits[0].document.as_ref().unwrap().href, "/page3");
+	}
+
+	#[test]
+	fn test_search_category_facets() {
+		let index = build_index(category_documents()).unwrap();
+
+		let options = SearchOptions {
+			max_results: 1,
+			categories: Some(vec!["Tutorials".to_string()]),
+			facets: true,
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "extension", &options).unwrap();
+		assert_eq!(
+			results.facets,
+			vec![
+				CategoryFacet {
+					category: "Guides".to_string(),
+					count: 2,
+				},
+				CategoryFacet {
+					category: "Reference".to_string(),
+					count: 1,
+				},
+				CategoryFacet {
+					category: "Tutorials".to_string(),
+					count: 1,
+				},
+			]
+		);
+	}
+
+	// ========================================================================
+	// SECTION 14: Pagination Tests
+	// ========================================================================
+
+	#[test]
+	fn test_search_pagination() {
+		let mut documents = Vec::new();
+		for i in 0..25 {
+			documents.push(Document {
+				category: "Updates".to_string(),
+				keywords: Some(vec!["release".to_string()]),
+				..doc(
+					&format!("Release Notes {}", i),
+					&format!("/updates/{}", i),
+					"Changes in this release",
+				)
+			});
+		}
+
+		let index = build_index(documents).unwrap();
+
+		let all = search(&index.view(), "release", 100).unwrap();
+		assert_eq!(all.len(), 25);
+
+		let mut paged = Vec::new();
+		for page in 0..3 {
+			let options = SearchOptions {
+				max_results: 10,
+				offset: page * 10,
+				..Default::default()
+			};
+			let results = search_with_options(&index.view(), "release", &options).unwrap();
+			assert_eq!(results.total, 25);
+			assert_eq!(results.hits.len(), if page < 2 { 10 } else { 5 });
+			paged.extend(results.hits);
+		}
+
+		// pages line up with a single large search
+		let all: Vec<&str> = all
+			.iter()
+			.map(|h| h.document.as_ref().unwrap().href.as_str())
+			.collect();
+		let paged: Vec<&str> = paged
+			.iter()
+			.map(|h| h.document.as_ref().unwrap().href.as_str())
+			.collect();
+		assert_eq!(all, paged);
+
+		let options = SearchOptions {
+			offset: 30,
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "release", &options).unwrap();
+		assert_eq!(results.total, 25);
+		assert!(results.hits.is_empty());
+	}
+
+	// ========================================================================
+	// SECTION 15: Snippet Tests
+	// ========================================================================
+
+	fn snippet_documents() -> Vec<Document> {
+		let filler = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. ".repeat(8);
+		vec![Document {
+			category: "Guides".to_string(),
+			keywords: Some(vec!["tunnel".to_string()]),
+			..doc(
+				"Remote Development",
+				"/guides/remote",
+				&format!(
+					"{}Use the tunnel command to reach your machine & open <folders>. {}",
+					filler, filler
+				),
+			)
+		}]
+	}
+
+	#[test]
+	fn test_search_snippet_highlights() {
+		let index = build_index(snippet_documents()).unwrap();
+
+		let options = SearchOptions {
+			snippet: Some(SnippetOptions {
+				length: 60,
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "tunnel", &options).unwrap();
+		assert_eq!(results.hits.len(), 1);
+
+		let hit = &results.hits[0];
+		assert!(hit.document.as_ref().unwrap().body.is_empty());
+		assert_eq!(hit.document.as_ref().unwrap().title, "Remote Development");
+
+		let snippet = hit.snippet.as_ref().unwrap();
+		assert!(snippet.text.starts_with('…') && snippet.text.ends_with('…'));
+		assert!(snippet.text.chars().count() <= 62);
+		// the body keyword "tunnel command" is highlighted as a whole
+		assert_eq!(utf16_highlights(snippet), vec!["tunnel", "command"]);
+	}
+
+	/// The highlighted words of a snippet, read the way JavaScript would.
+	fn utf16_highlights(snippet: &crate::Snippet) -> Vec<String> {
+		let text: Vec<u16> = snippet.text.encode_utf16().collect();
+		snippet
+			.highlights
+			.iter()
+			.map(|h| String::from_utf16(&text[h.clone()]).unwrap())
+			.collect()
+	}
+
+	#[test]
+	fn test_snippet_highlights_are_utf16_offsets() {
+		let options = SnippetOptions {
+			length: 100,
+			..Default::default()
+		};
+		let body = "Le café ouvre à 8h 🚀 puis le tunnel ferme.";
+		let snippet = crate::snippet::build_snippet(body, &["tunnel"], Stemmer::None, &options);
+		assert_eq!(utf16_highlights(&snippet), vec!["tunnel"]);
+		assert_eq!(snippet.highlights, vec![30..36]);
+	}
+
+	#[test]
+	fn test_search_snippet_mark_tags() {
+		let index = build_index(snippet_documents()).unwrap();
+
+		let options = SearchOptions {
+			snippet: Some(SnippetOptions {
+				length: 80,
+				mark: true,
+			}),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "tunnel", &options).unwrap();
+		let snippet = results.hits[0].snippet.as_ref().unwrap();
+
+		assert!(
+			snippet
+				.text
+				.contains("the <mark>tunnel</mark> <mark>command</mark> to")
+		);
+		assert!(snippet.text.contains("&amp; open &lt;folders&gt;."));
+		assert!(snippet.highlights.is_empty());
+	}
+
+	#[test]
+	fn test_search_snippet_without_body_match() {
+		// Test that a title-only match falls back to the start of the body
+		let index = build_index(snippet_documents()).unwrap();
+
+		let options = SearchOptions {
+			snippet: Some(SnippetOptions {
+				length: 20,
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "remote", &options).unwrap();
+		let snippet = results.hits[0].snippet.as_ref().unwrap();
+
+		assert_eq!(snippet.text, "Lorem ipsum dolor…");
+		assert!(snippet.highlights.is_empty());
+
+		// no snippet unless asked for
+		let results = search(&index.view(), "remote", 10).unwrap();
+		assert!(results[0].snippet.is_none());
+		assert!(!results[0].document.as_ref().unwrap().body.is_empty());
+	}
+
+	// ========================================================================
+	// SECTION 16: Lazy Document Decoding Tests
+	// ========================================================================
+
+	#[test]
+	fn test_search_ids_only() {
+		let index = build_index(category_documents()).unwrap();
+
+		let options = SearchOptions {
+			mode: ResultMode::Ids,
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "extension", &options).unwrap();
+		assert_eq!(results.hits.len(), 4);
+		assert!(results.hits.iter().all(|h| h.document.is_none()));
+
+		// ids line up with the full documents
+		let full = search(&index.view(), "extension", 10).unwrap();
+		for (hit, full_hit) in results.hits.iter().zip(&full) {
+			assert_eq!(hit.id, full_hit.id);
+			assert_eq!(hit.score, full_hit.score);
+			let document = get_document(&index.view(), hit.id, &[Field::Href]).unwrap();
+			assert_eq!(document.href, full_hit.document.as_ref().unwrap().href);
+		}
+	}
+
+	#[test]
+	fn test_get_document_fields() {
+		let index = build_index(category_documents()).unwrap();
+
+		let document = get_document(&index.view(), 1, &[Field::Title, Field::Category]).unwrap();
+		assert_eq!(document.title, "Extension Page 1");
+		assert_eq!(document.category, "Reference");
+		assert!(document.href.is_empty());
+		assert!(document.body.is_empty());
+		assert!(document.keywords.is_none());
+
+		let document = get_document(
+			&index.view(),
+			3,
+			&[Field::Title, Field::Category, Field::Href, Field::Body],
+		)
+		.unwrap();
+		assert_eq!(document.href, "/page3");
+		assert_eq!(document.body, "How to write an extension");
+
+		assert!(get_document(&index.view(), 4, &[Field::Title]).is_err());
+	}
+
+	// ========================================================================
+	// SECTION 17: Zero-Copy Index View
+	// ========================================================================
+
+	#[test]
+	fn test_index_view_borrows_serialized_bytes() {
+		let index = build_index(category_documents()).unwrap();
+		let bytes = index.to_bytes().unwrap();
+		let view = IndexView::from_bytes(&bytes).unwrap();
+
+		let range = bytes.as_ptr_range();
+		assert!(range.contains(&view.fst.as_ptr()));
+		assert!(range.contains(&view.postings.as_ptr()));
+		assert!(range.contains(&view.document_strings.data.as_ptr()));
+		assert!(range.contains(&view.categories[0].as_ptr()));
+		assert_eq!(view.document_count(), 4);
+	}
+
+	#[test]
+	fn test_index_view_matches_owned_index() {
+		let index = build_index(category_documents()).unwrap();
+		let bytes = index.to_bytes().unwrap();
+		let view = IndexView::from_bytes(&bytes).unwrap();
+
+		let options = SearchOptions {
+			facets: true,
+			snippet: Some(SnippetOptions::default()),
+			..Default::default()
+		};
+		let from_view = search_with_options(&view, "extension", &options).unwrap();
+		let from_index = search_with_options(&index.view(), "extension", &options).unwrap();
+		assert_eq!(from_view.total, from_index.total);
+		assert_eq!(from_view.facets, from_index.facets);
+		for (a, b) in from_view.hits.iter().zip(&from_index.hits) {
+			assert_eq!(a.id, b.id);
+			assert_eq!(a.score, b.score);
+			assert_eq!(
+				a.snippet.as_ref().unwrap().text,
+				b.snippet.as_ref().unwrap().text
+			);
+			assert_eq!(
+				a.document.as_ref().unwrap().title,
+				b.document.as_ref().unwrap().title
+			);
+		}
+
+		// postings survive the roundtrip unchanged
+		for keyword_index in 0..index.posting_offsets.len() / 4 {
+			assert!(
+				view
+					.postings(keyword_index)
+					.eq(index.view().postings(keyword_index))
+			);
+		}
+	}
+
+	#[test]
+	fn test_index_view_rejects_malformed_bytes() {
+		assert!(IndexView::from_bytes(&[]).is_err());
+		assert!(IndexView::from_bytes(&[0xff; 16]).is_err());
+
+		let index = build_index(category_documents()).unwrap();
+		let bytes = index.to_bytes().unwrap();
+		assert!(IndexView::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+	}
+
+	#[test]
+	fn test_index_view_rejects_out_of_range_references() {
+		let corrupted = |corrupt: &dyn Fn(&mut Index)| {
+			let mut index = build_index(category_documents()).unwrap();
+			corrupt(&mut index);
+			IndexView::from_bytes(&index.to_bytes().unwrap())
+				.map(|_| ())
+				.map_err(|e| e.to_string())
+		};
+		assert!(corrupted(&|_| {}).is_ok());
+
+		let error = corrupted(&|index| index.postings[..4].copy_from_slice(&99u32.to_le_bytes()));
+		assert!(error.unwrap_err().contains("posting document"));
+		let error = corrupted(&|index| {
+			let end = index.posting_offsets.len();
+			index.posting_offsets[end - 4..].copy_from_slice(&u32::MAX.to_le_bytes());
+		});
+		assert!(error.unwrap_err().contains("posting offset"));
+		let error =
+			corrupted(&|index| index.document_categories[..4].copy_from_slice(&9u32.to_le_bytes()));
+		assert!(error.unwrap_err().contains("category"));
+		let error = corrupted(&|index| index.posting_offsets.truncate(4));
+		assert!(error.unwrap_err().contains("keyword"));
+		let error = corrupted(&|index| index.postings[7] = 6);
+		assert!(error.unwrap_err().contains("posting field"));
+		// a code past the end of the FSST symbol table
+		assert!(corrupted(&|index| index.document_strings.data[0] = 254).is_err());
+	}
+
+	// ========================================================================
+	// SECTION 18: Index Options
+	// ========================================================================
+
+	/// A long reference page with more body phrases than the default budgets keep.
+	fn reference_document() -> Document {
+		Document {
+			category: "Reference".to_string(),
+			..doc(
+				"Settings Reference",
+				"/docs/settings",
+				"Set the font size and the minimap and the bracket pair colorization and the \
+				telemetry and the word wrap and the sticky scroll and the cursor and the \
+				format on save and the auto save delay and the ruler and the tab size and the \
+				indentation guides and the theme and the zoom level and the breadcrumbs.",
+			)
+		}
+	}
+
+	/// Body keywords of the only document in `index`.
+	fn body_keywords(index: &Index) -> Vec<String> {
+		let view = index.view();
+		let map = fst::Map::new(view.fst).unwrap();
+		let mut keywords = Vec::new();
+		let mut stream = map.stream();
+		while let Some((keyword, keyword_index)) = fst::Streamer::next(&mut stream) {
+			if view
+				.postings(keyword_index as usize)
+				.any(|p| p.fields & Field::Body.bit() != 0)
+			{
+				keywords.push(String::from_utf8(keyword.to_vec()).unwrap());
+			}
+		}
+		keywords
+	}
+
+	#[test]
+	fn test_index_options_body_budgets() {
+		let default = body_keywords(&build_index(vec![reference_document()]).unwrap());
+		let count_words = |keywords: &[String], words: usize| {
+			keywords
+				.iter()
+				.filter(|k| k.split(' ').count() == words)
+				.count()
+		};
+		assert!(count_words(&default, 1) <= 5);
+		assert!(count_words(&default, 2) <= 3);
+		assert_eq!(count_words(&default, 3), 0);
+
+		let options = IndexOptions {
+			body_budgets: vec![20, 20, 20],
+			max_phrase_length: 3,
+			..Default::default()
+		};
+		let wide =
+			body_keywords(&build_index_with_options(vec![reference_document()], &options).unwrap());
+		assert!(wide.len() > default.len());
+		assert!(count_words(&wide, 3) > 0);
+		assert!(default.iter().all(|k| wide.contains(k)));
+
+		// longer phrases share the last budget
+		let options = IndexOptions {
+			body_budgets: vec![0, 1],
+			max_phrase_length: 3,
+			..Default::default()
+		};
+		let shared =
+			body_keywords(&build_index_with_options(vec![reference_document()], &options).unwrap());
+		assert_eq!(count_words(&shared, 1), 0);
+		assert_eq!(count_words(&shared, 2), 1);
+		assert_eq!(count_words(&shared, 3), 1);
+	}
+
+	#[test]
+	fn test_index_options_weights() {
+		let options = IndexOptions {
+			weights: KeywordWeights {
+				title: 20.0,
+				href: 95.0,
+				..Default::default()
+			},
+			..Default::default()
+		};
+		let index = build_index_with_options(vec![reference_document()], &options).unwrap();
+
+		let results = search(&index.view(), "settings", 10).unwrap();
+		assert_eq!(results[0].score, 20.0);
+		let results = search(&index.view(), "href:docs", 10).unwrap();
+		assert_eq!(results[0].score, 95.0);
+	}
+
+	#[test]
+	fn test_index_options_from_json() {
+		let options: IndexOptions =
+			serde_json::from_str(r#"{ "weights": { "title": 50 }, "maxPhraseLength": 3 }"#).unwrap();
+		assert_eq!(options.weights.title, 50.0);
+		assert_eq!(options.weights.keywords, 100.0);
+		assert_eq!(options.body_budgets, vec![5, 3]);
+		assert_eq!(options.max_phrase_length, 3);
+	}
+
+	// ========================================================================
+	// SECTION 19: Full-Text Indexing
+	// ========================================================================
+
+	fn full_text_documents() -> Vec<Document> {
+		vec![
+			Document {
+				category: "Remote".to_string(),
+				..doc(
+					"Remote Tunnels",
+					"/docs/tunnels",
+					"Tunnels forward a port from a remote machine. The tunnel keeps the port \
+					open while the machine sleeps, and the port is closed on exit or when the laptop \
+					sleeps.",
+				)
+			},
+			Document {
+				category: "Terminal".to_string(),
+				..doc(
+					"Terminal Basics",
+					"/docs/terminal",
+					"Open an integrated shell next to your editor.",
+				)
+			},
+		]
+	}
+
+	#[test]
+	fn test_full_text_finds_every_body_word() {
+		let compact = build_index(full_text_documents()).unwrap();
+		let options = IndexOptions {
+			full_text: true,
+			..Default::default()
+		};
+		let full = build_index_with_options(full_text_documents(), &options).unwrap();
+
+		// "sleeps" is not among the RAKE keywords of the first document
+		assert!(search(&compact.view(), "sleeps", 10).unwrap().is_empty());
+		let results = search(&full.view(), "sleeps", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].id, 0);
+
+		let results = search(&full.view(), "integrated", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].id, 1);
+
+		// stop words stay out of the index
+		assert!(search(&full.view(), "the", 10).unwrap().is_empty());
+		assert!(full.fst.len() > compact.fst.len());
+	}
+
+	#[test]
+	fn test_full_text_term_frequency_and_document_length() {
+		let options = IndexOptions {
+			full_text: true,
+			..Default::default()
+		};
+		let index = build_index_with_options(full_text_documents(), &options).unwrap();
+		let view = index.view();
+
+		assert_eq!(view.document_length(0), Some(30));
+		assert_eq!(view.document_length(1), Some(8));
+		assert_eq!(view.document_length(2), None);
+
+		let results = search(&view, "port", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		let port = results[0]
+			.matches
+			.iter()
+			.find(|m| m.keyword == "port")
+			.unwrap();
+		assert_eq!(port.term_frequency, 3);
+
+		// body words that RAKE did not pick are scored by term frequency
+		let results = search(&view, "sleeps", 10).unwrap();
+		assert_eq!(results[0].score, 2.0);
+		assert_eq!(results[0].matches[0].term_frequency, 2);
+
+		// title keywords also count their body occurrences
+		let results = search(&view, "terminal", 10).unwrap();
+		assert_eq!(results[0].matches[0].term_frequency, 0);
+		let results = search(&view, "editor", 10).unwrap();
+		assert_eq!(results[0].matches[0].term_frequency, 1);
+	}
+
+	// ========================================================================
+	// SECTION 20: BM25 Ranking
+	// ========================================================================
+
+	fn bm25_options() -> IndexOptions {
+		IndexOptions {
+			full_text: true,
+			ranking: Ranking::Bm25,
+			..Default::default()
+		}
+	}
+
+	fn bm25_documents() -> Vec<Document> {
+		let document = |title: &str, body: &str| Document {
+			category: "Docs".to_string(),
+			..doc(
+				title,
+				&format!("/{}", title.to_lowercase().replace(' ', "-")),
+				body,
+			)
+		};
+		vec![
+			document(
+				"Editor Overview",
+				"The editor shows files in tabs. A long page that mentions the cache once among \
+				many other topics such as layout, panels, views, themes, fonts and icons.",
+			),
+			document(
+				"Cache Settings",
+				"Clear the cache when the cache grows. The cache lives on disk.",
+			),
+			document(
+				"Workspace Layout",
+				"Panels and views are arranged in the workbench layout with tabs.",
+			),
+		]
+	}
+
+	#[test]
+	fn test_bm25_term_frequency_and_length() {
+		let index = build_index_with_options(bm25_documents(), &bm25_options()).unwrap();
+		let results = search(&index.view(), "cache", 10).unwrap();
+
+		assert_eq!(results.len(), 2);
+		// more occurrences in a shorter body, and a title match, rank first
+		assert_eq!(results[0].id, 1);
+		assert_eq!(results[1].id, 0);
+		assert!(results[0].score > results[1].score);
+		assert!(results[1].score > 0.0);
+	}
+
+	#[test]
+	fn test_bm25_rare_terms_weigh_more() {
+		let index = build_index_with_options(bm25_documents(), &bm25_options()).unwrap();
+
+		// "tabs" occurs in two documents, "workbench" only in one
+		let tabs = search(&index.view(), "tabs", 10).unwrap();
+		let workbench = search(&index.view(), "workbench", 10).unwrap();
+		assert_eq!(tabs.len(), 2);
+		assert_eq!(workbench.len(), 1);
+		let tabs_score = tabs.iter().find(|h| h.id == 2).unwrap().score;
+		assert!(workbench[0].score > tabs_score);
+	}
+
+	#[test]
+	fn test_bm25_field_boosts() {
+		let options = IndexOptions {
+			bm25: Bm25Parameters {
+				boosts: FieldBoosts {
+					title: 10.0,
+					..Default::default()
+				},
+				..Default::default()
+			},
+			..bm25_options()
+		};
+		let boosted = build_index_with_options(bm25_documents(), &options).unwrap();
+		let plain = build_index_with_options(bm25_documents(), &bm25_options()).unwrap();
+
+		let boosted = search(&boosted.view(), "layout", 10).unwrap();
+		let plain = search(&plain.view(), "layout", 10).unwrap();
+		let score = |hits: &[crate::SearchHit], id| hits.iter().find(|h| h.id == id).unwrap().score;
+
+		// the title match of document 2 gains, the body-only match of document 0 does not
+		assert!(score(&boosted, 2) > score(&plain, 2));
+		assert_eq!(score(&boosted, 0), score(&plain, 0));
+	}
+
+	#[test]
+	fn test_bm25_survives_serialization() {
+		let index = build_index_with_options(bm25_documents(), &bm25_options()).unwrap();
+		let bytes = index.to_bytes().unwrap();
+		let view = IndexView::from_bytes(&bytes).unwrap();
+		assert_eq!(view.ranking, Ranking::Bm25);
+
+		let expected = search(&index.view(), "cache", 10).unwrap();
+		let results = search(&view, "cache", 10).unwrap();
+		assert_eq!(results.len(), expected.len());
+		for (a, b) in results.iter().zip(&expected) {
+			assert_eq!(a.id, b.id);
+			assert_eq!(a.score, b.score);
+		}
+
+		// keyword ranking is still the default
+		let index = build_index(bm25_documents()).unwrap();
+		assert_eq!(index.view().ranking, Ranking::Keyword);
+	}
+
+	// ========================================================================
+	// SECTION 21: Score Precision
+	// ========================================================================
+
+	#[test]
+	fn test_multi_keyword_scores_do_not_saturate() {
+		// Both documents match three strong keywords, which used to saturate at 255
+		// and fall back to document order
+		let documents = vec![
+			Document {
+				category: "Docs".to_string(),
+				keywords: Some(vec!["alpha".to_string(), "beta".to_string()]),
+				..doc("Gamma Guide", "/gamma-guide", "Notes")
+			},
+			Document {
+				category: "Docs".to_string(),
+				keywords: Some(vec![
+					"alpha".to_string(),
+					"beta".to_string(),
+					"gamma".to_string(),
+				]),
+				..doc("Reference", "/reference", "Notes")
+			},
+		];
+		let index = build_index(documents).unwrap();
+
+		let results = search(&index.view(), "alpha beta gamma", 10).unwrap();
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].id, 1);
+		assert_eq!(results[0].score, 300.0);
+		assert_eq!(results[1].id, 0);
+		assert_eq!(results[1].score, 290.0);
+	}
+
+	#[test]
+	fn test_fractional_and_large_scores_are_kept() {
+		let options = IndexOptions {
+			full_text: true,
+			weights: KeywordWeights {
+				body: 0.5,
+				keywords: 1000.0,
+				..Default::default()
+			},
+			..Default::default()
+		};
+		let documents = vec![Document {
+			category: "Docs".to_string(),
+			keywords: Some(vec!["console".to_string()]),
+			..doc(
+				"Terminal",
+				"/terminal",
+				"The shell opens when the machine sleeps and the laptop sleeps and the server sleeps.",
+			)
+		}];
+		let index = build_index_with_options(documents, &options).unwrap();
+
+		// body words that RAKE did not pick score half their term frequency
+		let results = search(&index.view(), "sleeps", 10).unwrap();
+		assert_eq!(results[0].score, 1.5);
+
+		// explicit keywords are no longer capped at 255
+		let results = search(&index.view(), "console", 10).unwrap();
+		assert_eq!(results[0].score, 1000.0);
+	}
+
+	// ========================================================================
+	// SECTION 22: Typo Tolerance
+	// ========================================================================
+
+	fn typo_documents() -> Vec<Document> {
+		let document = |title: &str, keyword: &str| Document {
+			category: "Docs".to_string(),
+			keywords: Some(vec![keyword.to_string()]),
+			..doc(title, &format!("/{}", keyword), "")
+		};
+		vec![
+			document("Workspace Configuration", "configuration"),
+			document("Editor Settings", "config"),
+			document("Pets", "cat"),
+		]
+	}
+
+	#[test]
+	fn test_edit_distance() {
+		assert_eq!(edit_distance("config", "config"), 0);
+		assert_eq!(edit_distance("conflg", "config"), 1);
+		assert_eq!(edit_distance("confgi", "config"), 2);
+		assert_eq!(edit_distance("", "abc"), 3);
+		assert_eq!(edit_distance("café", "cafe"), 1);
+	}
+
+	#[test]
+	fn test_typos_scale_with_word_length() {
+		let tolerance = TypoTolerance::default();
+		assert_eq!(tolerance.typos("cat"), 0);
+		assert_eq!(tolerance.typos("conf"), 1);
+		assert_eq!(tolerance.typos("setting"), 1);
+		assert_eq!(tolerance.typos("settings"), 2);
+
+		let index = build_index(typo_documents()).unwrap();
+		let ids = |query: &str| -> Vec<usize> {
+			search(&index.view(), query, 10)
+				.unwrap()
+				.iter()
+				.map(|h| h.id)
+				.collect()
+		};
+
+		// short words are never fuzzy
+		assert!(ids("cot").is_empty());
+		// mid-length words allow one typo, not two
+		assert_eq!(ids("conflg"), vec![1]);
+		assert!(ids("cpnflg").is_empty());
+		// long words allow two typos
+		assert_eq!(ids("confgiuration"), vec![0]);
+	}
+
+	#[test]
+	fn test_typo_penalty() {
+		let index = build_index(typo_documents()).unwrap();
+
+		let exact = search(&index.view(), "config", 10).unwrap();
+		let exact = exact.iter().find(|h| h.id == 1).unwrap();
+		assert_eq!(exact.score, 100.0);
+
+		// one typo costs a quarter of the score, on top of the prefix penalty
+		let fuzzy = search(&index.view(), "cpnfig", 10).unwrap();
+		assert_eq!(fuzzy[0].id, 1);
+		assert!((fuzzy[0].score - 60.0).abs() < 1e-3);
+		assert_eq!(fuzzy[0].matches[0].match_type, MatchType::Fuzzy);
+
+		let options = SearchOptions {
+			typo_tolerance: Some(TypoTolerance {
+				typo_penalty: 0.5,
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "cpnfig", &options).unwrap();
+		assert!((results.hits[0].score - 40.0).abs() < 1e-3);
+
+		let options = SearchOptions {
+			typo_tolerance: None,
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "cpnfig", &options).unwrap();
+		assert_eq!(results.total, 0);
+	}
+
+	// ========================================================================
+	// SECTION 23: Match Type Ranking
+	// ========================================================================
+
+	#[test]
+	fn test_exact_match_ranks_first() {
+		// "configuration" (document 0) only starts with the query word, while
+		// document 1 has the exact keyword; both used to score 100
+		let index = build_index(typo_documents()).unwrap();
+		let results = search(&index.view(), "config", 10).unwrap();
+
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].id, 1);
+		assert_eq!(results[0].matches[0].match_type, MatchType::Exact);
+		assert_eq!(results[0].score, 100.0);
+		assert_eq!(results[1].id, 0);
+		assert_eq!(results[1].matches[0].match_type, MatchType::Prefix);
+		assert_eq!(results[1].score, 80.0);
+	}
+
+	#[test]
+	fn test_prefix_match_ranks_above_fuzzy() {
+		let document = |keyword: &str| Document {
+			category: "Docs".to_string(),
+			keywords: Some(vec![keyword.to_string()]),
+			..doc("Page", &format!("/{}", keyword), "")
+		};
+		let index = build_index(vec![document("bindings"), document("bending")]).unwrap();
+
+		let results = search(&index.view(), "binding", 10).unwrap();
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].id, 0);
+		assert_eq!(results[0].matches[0].match_type, MatchType::Prefix);
+		assert_eq!(results[1].id, 1);
+		assert_eq!(results[1].matches[0].match_type, MatchType::Fuzzy);
+		assert!(results[0].score > results[1].score);
+	}
+
+	#[test]
+	fn test_equal_scores_prefer_stronger_match_type() {
+		// without a prefix penalty the scores tie, and the exact match still wins
+		let index = build_index(typo_documents()).unwrap();
+		let options = SearchOptions {
+			prefix_penalty: 0.0,
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "config", &options).unwrap();
+		assert_eq!(results.hits[0].score, results.hits[1].score);
+		assert_eq!(results.hits[0].id, 1);
+	}
+
+	#[test]
+	fn test_prefix_penalty_weights_unequal_scores() {
+		// the title prefix match scores far more than the body keyword, but only
+		// the body matches the query word exactly
+		let documents = vec![
+			Document {
+				category: "Docs".to_string(),
+				..doc("Configuration", "/configuration", "")
+			},
+			Document {
+				category: "Docs".to_string(),
+				..doc("Settings", "/settings", "Open the config.")
+			},
+		];
+		let index = build_index(documents).unwrap();
+
+		// the penalty weights the title score, which stays far ahead
+		let results = search(&index.view(), "config", 10).unwrap();
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].id, 0);
+		assert_eq!(results[0].matches[0].match_type, MatchType::Prefix);
+		assert_eq!(results[1].id, 1);
+		assert_eq!(results[1].matches[0].match_type, MatchType::Exact);
+		assert!(results[0].score > results[1].score);
+
+		// a heavier penalty lets the exact match win
+		let options = SearchOptions {
+			prefix_penalty: 0.999,
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "config", &options).unwrap();
+		assert_eq!(results.hits[0].id, 1);
+		assert!(results.hits[0].score > results.hits[1].score);
+	}
+
+	// ========================================================================
+	// SECTION 24: Stemming
+	// ========================================================================
+
+	#[test]
+	fn test_porter_stemmer_vocabulary() {
+		// Examples from the paper describing the algorithm
+		let cases = [
+			("caresses", "caress"),
+			("ponies", "poni"),
+			("ties", "ti"),
+			("cats", "cat"),
+			("feed", "feed"),
+			("agreed", "agre"),
+			("plastered", "plaster"),
+			("motoring", "motor"),
+			("sing", "sing"),
+			("conflated", "conflat"),
+			("sized", "size"),
+			("hopping", "hop"),
+			("falling", "fall"),
+			("filing", "file"),
+			("happy", "happi"),
+			("sky", "sky"),
+			("relational", "relat"),
+			("conditional", "condit"),
+			("digitizer", "digit"),
+			("vietnamization", "vietnam"),
+			("predication", "predic"),
+			("operator", "oper"),
+			("decisiveness", "decis"),
+			("hopefulness", "hope"),
+			("sensibiliti", "sensibl"),
+			("triplicate", "triplic"),
+			("formative", "form"),
+			("electrical", "electr"),
+			("goodness", "good"),
+			("allowance", "allow"),
+			("airliner", "airlin"),
+			("adjustable", "adjust"),
+			("replacement", "replac"),
+			("adoption", "adopt"),
+			("communism", "commun"),
+			("effective", "effect"),
+			("probate", "probat"),
+			("rate", "rate"),
+			("cease", "ceas"),
+			("controll", "control"),
+			("roll", "roll"),
+			("installing", "instal"),
+			("installs", "instal"),
+			("installation", "instal"),
+		];
+		for (word, stem) in cases {
+			assert_eq!(Stemmer::Porter.stem(word), stem, "stem of {}", word);
+		}
+
+		// short, non-ASCII and multi-word input
+		assert_eq!(Stemmer::Porter.stem("is"), "is");
+		assert_eq!(Stemmer::Porter.stem("café"), "café");
+		assert_eq!(Stemmer::Porter.stem("running tests"), "run test");
+		assert_eq!(Stemmer::None.stem("running tests"), "running tests");
+	}
+
+	fn stemming_documents() -> Vec<Document> {
+		vec![
+			Document {
+				category: "Guides".to_string(),
+				..doc(
+					"Installing Extensions",
+					"/docs/extensions",
+					"Browse the marketplace and pick extensions to install. Running tasks \
+					needs no setup.",
+				)
+			},
+			Document {
+				category: "Guides".to_string(),
+				..doc(
+					"Installation Troubleshooting",
+					"/docs/troubleshooting",
+					"Fix failed installs.",
+				)
+			},
+		]
+	}
+
+	#[test]
+	fn test_stemming_matches_word_forms() {
+		let plain = build_index(stemming_documents()).unwrap();
+		let options = IndexOptions {
+			stemmer: Stemmer::Porter,
+			..Default::default()
+		};
+		let stemmed = build_index_with_options(stemming_documents(), &options).unwrap();
+
+		let ids = |index: &Index, query: &str| -> Vec<usize> {
+			let mut ids: Vec<usize> = search(&index.view(), query, 10)
+				.unwrap()
+				.iter()
+				.map(|h| h.id)
+				.collect();
+			ids.sort();
+			ids
+		};
+
+		// "installs" is neither a prefix nor a typo of "installing" or "installation"
+		assert!(ids(&plain, "installs").is_empty());
+		assert_eq!(ids(&stemmed, "installs"), vec![0, 1]);
+		assert_eq!(ids(&stemmed, "installed"), vec![0, 1]);
+
+		// phrases are stemmed on both sides
+		assert_eq!(ids(&stemmed, "\"installed extension\""), vec![0]);
+	}
+
+	#[test]
+	fn test_stemmer_is_stored_in_index() {
+		let options = IndexOptions {
+			stemmer: Stemmer::Porter,
+			..Default::default()
+		};
+		let index = build_index_with_options(stemming_documents(), &options).unwrap();
+		let bytes = index.to_bytes().unwrap();
+		let view = IndexView::from_bytes(&bytes).unwrap();
+		assert_eq!(view.stemmer, Stemmer::Porter);
+
+		let results = search(&view, "troubleshoot", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].id, 1);
+	}
+
+	#[test]
+	fn test_stemmed_keyword_is_indexed_once() {
+		// stemming is not idempotent, "databases" and "database" both stem to
+		// "databas", which would stem again to "databa"
+		let options = IndexOptions {
+			stemmer: Stemmer::Porter,
+			..Default::default()
+		};
+		let documents = vec![
+			doc("Databases and database", "/databases", ""),
+			doc("Database", "/database", ""),
+		];
+		let index = build_index_with_options(documents, &options).unwrap();
+		let view = index.view();
+		let map = fst::Map::new(view.fst).unwrap();
+		let keyword_index = map.get("databas").unwrap() as usize;
+		assert_eq!(view.postings(keyword_index).count(), 2);
+
+		let results = search(&view, "database", 10).unwrap();
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].score, results[1].score);
+	}
+
+	#[test]
+	fn test_stemmed_snippet_highlights() {
+		let options = IndexOptions {
+			stemmer: Stemmer::Porter,
+			full_text: true,
+			..Default::default()
+		};
+		let index = build_index_with_options(stemming_documents(), &options).unwrap();
+		let search_options = SearchOptions {
+			snippet: Some(SnippetOptions {
+				mark: true,
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "run", &search_options).unwrap();
+		assert_eq!(results.hits.len(), 1);
+		let text = &results.hits[0].snippet.as_ref().unwrap().text;
+		assert!(text.contains("<mark>Running</mark>"), "{}", text);
+	}
+
+	// ========================================================================
+	// SECTION 25: Stop Word Languages
+	// ========================================================================
+
+	fn german_document(language: Option<&str>) -> Document {
+		Document {
+			category: "Anleitungen".to_string(),
+			language: language.map(str::to_string),
+			..doc(
+				"Erweiterungen",
+				"/de/erweiterungen",
+				"Öffnen Sie die Ansicht und wählen Sie eine Erweiterung aus der Liste",
+			)
+		}
+	}
+
+	/// All keywords of an index.
+	fn all_keywords(index: &Index) -> Vec<String> {
+		let map = fst::Map::new(index.fst.as_slice()).unwrap();
+		let mut keywords = Vec::new();
+		let mut stream = map.stream();
+		while let Some((keyword, _)) = fst::Streamer::next(&mut stream) {
+			keywords.push(String::from_utf8(keyword.to_vec()).unwrap());
+		}
+		keywords
+	}
+
+	#[test]
+	fn test_stop_word_lists_are_bundled() {
+		for (code, list) in crate::STOP_WORDS {
+			let words: Vec<String> = crate::parse_stop_words(list).collect();
+			assert!(words.len() > 50, "{} has {} stop words", code, words.len());
+			assert!(!words.iter().any(|w| w.starts_with('#')));
+		}
+	}
+
+	#[test]
+	fn test_document_language_selects_stop_words() {
+		let english = all_keywords(&build_index(vec![german_document(None)]).unwrap());
+		let german = all_keywords(&build_index(vec![german_document(Some("de-DE"))]).unwrap());
+
+		// English stop words leave the German body as one long phrase, which is
+		// too long to be indexed
+		assert!(!english.contains(&"erweiterung".to_string()));
+		assert!(german.contains(&"erweiterung".to_string()));
+		assert!(german.contains(&"ansicht".to_string()));
+		assert!(
+			!german
+				.iter()
+				.any(|k| k.split(' ').any(|w| w == "sie" || w == "die"))
+		);
+
+		// the default language applies to documents without one
+		let options = IndexOptions {
+			language: "de".to_string(),
+			..Default::default()
+		};
+		let index = build_index_with_options(vec![german_document(None)], &options).unwrap();
+		assert_eq!(all_keywords(&index), german);
+	}
+
+	#[test]
+	fn test_unsupported_language_uses_default() {
+		let options = IndexOptions {
+			language: "de".to_string(),
+			..Default::default()
+		};
+		let german = build_index_with_options(vec![german_document(None)], &options).unwrap();
+		let unknown = build_index_with_options(vec![german_document(Some("xx"))], &options).unwrap();
+		assert_eq!(all_keywords(&unknown), all_keywords(&german));
+
+		// the default language itself must be supported
+		let options = IndexOptions {
+			language: "xx".to_string(),
+			..Default::default()
+		};
+		let error = build_index_with_options(vec![german_document(None)], &options).unwrap_err();
+		assert!(error.to_string().contains("'xx'"));
+	}
+
+	#[test]
+	fn test_custom_stop_words() {
+		let document = Document {
+			category: "Guides".to_string(),
+			..doc("Contoso Setup", "/setup", "Contoso tools install quickly")
+		};
+		let options = IndexOptions {
+			stop_words: crate::parse_stop_words("# product names\nContoso\n").collect(),
+			..Default::default()
+		};
+		let index = build_index_with_options(vec![document], &options).unwrap();
+		let keywords = all_keywords(&index);
+		assert!(!keywords.iter().any(|k| k.contains("contoso")));
+		assert!(keywords.contains(&"setup".to_string()));
+	}
+
+	// ========================================================================
+	// SECTION 26: Unicode Tokenization
+	// ========================================================================
+
+	#[test]
+	fn test_tokenize_folds_diacritics() {
+		assert_eq!(
+			crate::tokenize::words("Café NAÏVE ﬁle, Straße!"),
+			vec!["cafe", "naive", "file", "straße"]
+		);
+		// dots and apostrophes inside words are kept, hyphens split
+		assert_eq!(
+			crate::tokenize::words("node.js isn't well-known"),
+			vec!["node.js", "isn't", "well", "known"]
+		);
+	}
+
+	#[test]
+	fn test_tokenize_cjk_bigrams() {
+		assert_eq!(
+			crate::tokenize::words("全文検索、日本 と Rust"),
+			vec!["全文", "文検", "検索", "日本", "と", "rust"]
+		);
+
+		let tokens = crate::tokenize::tokens("Rust全文");
+		assert_eq!(tokens[0], (0..4, "rust".to_string()));
+		assert_eq!(tokens[1], (4..10, "全文".to_string()));
+	}
+
+	fn unicode_documents() -> Vec<Document> {
+		vec![
+			Document {
+				category: "Food".to_string(),
+				..doc("Café Menu", "/cafe", "Crème brûlée and coffee")
+			},
+			Document {
+				category: "ドキュメント".to_string(),
+				language: Some("ja".to_string()),
+				..doc(
+					"全文検索エンジン",
+					"/ja/search",
+					"日本語の全文検索エンジンです",
+				)
+			},
+		]
+	}
+
+	#[test]
+	fn test_search_ignores_diacritics() {
+		let index = build_index(unicode_documents()).unwrap();
+
+		for query in ["cafe", "CAFÉ", "café"] {
+			let results = search(&index.view(), query, 10).unwrap();
+			assert_eq!(results.len(), 1, "{}", query);
+			assert_eq!(results[0].document.as_ref().unwrap().href, "/cafe");
+		}
+	}
+
+	#[test]
+	fn test_search_cjk_text() {
+		let options = IndexOptions {
+			full_text: true,
+			..Default::default()
+		};
+		let index = build_index_with_options(unicode_documents(), &options).unwrap();
+
+		let results = search(&index.view(), "検索", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/ja/search");
+
+		// longer queries are matched as a phrase of bigrams
+		let results = search(&index.view(), "全文検索", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		let results = search(&index.view(), "検索全文", 10).unwrap();
+		assert!(results.is_empty());
+	}
+
+	#[test]
+	fn test_search_cjk_body_without_full_text() {
+		let mut documents = unicode_documents();
+		documents[1].body = "高速な全文検索を提供します".to_string();
+		let index = build_index(documents).unwrap();
+
+		// only the body mentions speed, keyword extraction alone would drop it
+		let results = search(&index.view(), "高速", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/ja/search");
+		let results = search(&index.view(), "全文検索", 10).unwrap();
+		assert_eq!(results.len(), 1);
+	}
+
+	#[test]
+	fn test_scoped_terms_are_split_like_their_field() {
+		let documents = vec![doc("Installation", "/docs/install.html", "Run the setup")];
+		let index = build_index(documents).unwrap();
+
+		// href keywords are split on all punctuation, unlike other words
+		let results = search(&index.view(), "href:\"install.html\"", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		let results = search(&index.view(), "href:\"setup.html\"", 10).unwrap();
+		assert!(results.is_empty());
+	}
+
+	#[test]
+	fn test_scoped_identifiers_are_matched_as_phrases() {
+		let documents = vec![doc("Installation", "/docs/install.html", "Run the setup")];
+		let index = build_index(documents).unwrap();
+
+		// hrefs are not indexed as identifiers, so their parts are matched instead
+		for query in ["href:docs/install", "href:install.html"] {
+			let results = search(&index.view(), query, 10).unwrap();
+			assert_eq!(results.len(), 1, "{}", query);
+		}
+	}
+
+	#[test]
+	fn test_snippet_highlights_cjk_bigrams_once() {
+		let options = IndexOptions {
+			full_text: true,
+			..Default::default()
+		};
+		let index = build_index_with_options(unicode_documents(), &options).unwrap();
+		let search_options = SearchOptions {
+			snippet: Some(SnippetOptions {
+				mark: true,
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "全文検索", &search_options).unwrap();
+		let text = &results.hits[0].snippet.as_ref().unwrap().text;
+		assert_eq!(text, "日本語の<mark>全文検索</mark>エンジンです");
+	}
+
+	// ========================================================================
+	// SECTION 27: Code Identifiers
+	// ========================================================================
+
+	#[test]
+	fn test_identifier_parts() {
+		let parts = |word: &str| crate::tokenize::identifier(word).map(|(_, parts)| parts);
+		assert_eq!(
+			crate::tokenize::identifier("getUserById"),
+			Some((
+				"getuserbyid".to_string(),
+				vec!["get", "user", "by", "id"]
+					.into_iter()
+					.map(String::from)
+					.collect()
+			))
+		);
+		assert_eq!(
+			parts("`std::fs::read_to_string`,").unwrap(),
+			vec!["std", "fs", "read", "to", "string"]
+		);
+		assert_eq!(parts("HTTPServer").unwrap(), vec!["http", "server"]);
+		assert_eq!(
+			parts("max_retry_count").unwrap(),
+			vec!["max", "retry", "count"]
+		);
+
+		// plain words, abbreviations and hyphenated words are not identifiers
+		assert_eq!(parts("Hello"), None);
+		assert_eq!(parts("e.g."), None);
+		assert_eq!(parts("well-known"), None);
+	}
+
+	fn identifier_documents() -> Vec<Document> {
+		vec![
+			Document {
+				category: "Reference".to_string(),
+				..doc(
+					"Client Configuration",
+					"/client",
+					"Raise max_retry_count when getUserById times out, see std::fs::read_to_string",
+				)
+			},
+			Document {
+				category: "Guides".to_string(),
+				..doc(
+					"Retry Policies",
+					"/retry",
+					"Requests are retried with exponential backoff",
+				)
+			},
+		]
+	}
+
+	#[test]
+	fn test_search_identifier_parts() {
+		let index = build_index(identifier_documents()).unwrap();
+
+		let results = search(&index.view(), "count", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/client");
+
+		let results = search(&index.view(), "retry", 10).unwrap();
+		let hrefs: Vec<&str> = results
+			.iter()
+			.map(|hit| hit.document.as_ref().unwrap().href.as_str())
+			.collect();
+		assert_eq!(hrefs, vec!["/retry", "/client"]);
+
+		let results = search(&index.view(), "user", 10).unwrap();
+		assert_eq!(results.len(), 1);
+	}
+
+	#[test]
+	fn test_search_whole_identifier() {
+		let index = build_index(identifier_documents()).unwrap();
+
+		for query in ["getUserById", "max_retry_count", "std::fs::read_to_string"] {
+			let results = search(&index.view(), query, 10).unwrap();
+			assert_eq!(results.len(), 1, "{}", query);
+			assert_eq!(results[0].matches[0].match_type, MatchType::Exact);
+		}
+
+		// prefixes of the whole identifier match as well
+		let results = search(&index.view(), "getUser", 10).unwrap();
+		assert_eq!(results.len(), 1);
+	}
+
+	#[test]
+	fn test_snippet_highlights_identifier_parts() {
+		let index = build_index(identifier_documents()).unwrap();
+		let search_options = SearchOptions {
+			snippet: Some(SnippetOptions {
+				mark: true,
+				..Default::default()
+			}),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "count", &search_options).unwrap();
+		let text = &results.hits[0].snippet.as_ref().unwrap().text;
+		assert!(text.contains("<mark>max_retry_count</mark>"), "{}", text);
+	}
+
+	// ========================================================================
+	// SECTION 28: Synonyms
+	// ========================================================================
+
+	#[test]
+	fn test_parse_synonyms() {
+		let list = "# abbreviations\nk8s, kubernetes\n\n  Login,sign in ,\n";
+		let groups: Vec<Vec<String>> = crate::parse_synonyms(list).collect();
+		assert_eq!(
+			groups,
+			vec![
+				vec!["k8s".to_string(), "kubernetes".to_string()],
+				vec!["Login".to_string(), "sign in".to_string()],
+			]
+		);
+	}
+
+	fn synonym_documents() -> Vec<Document> {
+		vec![
+			Document {
+				category: "Guides".to_string(),
+				..doc(
+					"Kubernetes Deployment",
+					"/kubernetes",
+					"Deploy containers to a cluster",
+				)
+			},
+			Document {
+				category: "Guides".to_string(),
+				..doc(
+					"Sign in with SSO",
+					"/sso",
+					"Use your company account to sign in",
+				)
+			},
+			Document {
+				category: "Reference".to_string(),
+				..doc(
+					"Repository Settings",
+					"/repositories",
+					"Configure branches and permissions",
+				)
+			},
+		]
+	}
+
+	fn synonym_options() -> IndexOptions {
+		IndexOptions {
+			synonyms: crate::parse_synonyms("k8s, kubernetes\nlogin, sign in\nrepo, repository")
+				.collect(),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_search_expands_synonyms() {
+		let index = build_index_with_options(synonym_documents(), &synonym_options()).unwrap();
+
+		assert_eq!(
+			titles(&index.view(), "k8s", &SearchOptions::default()),
+			vec!["Kubernetes Deployment"]
+		);
+		assert_eq!(
+			titles(&index.view(), "repo", &SearchOptions::default()),
+			vec!["Repository Settings"]
+		);
+
+		// phrases expand to words and back
+		assert_eq!(
+			titles(&index.view(), "login", &SearchOptions::default()),
+			vec!["Sign in with SSO"]
+		);
+
+		// without synonyms nothing is found
+		let index = build_index(synonym_documents()).unwrap();
+		assert!(search(&index.view(), "k8s", 10).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_synonyms_survive_serialization() {
+		let index = build_index_with_options(synonym_documents(), &synonym_options()).unwrap();
+		let bytes = index.to_bytes().unwrap();
+		let view = IndexView::from_bytes(&bytes).unwrap();
+
+		assert_eq!(
+			titles(&view, "+k8s -login", &SearchOptions::default()),
+			vec!["Kubernetes Deployment"]
+		);
+		assert_eq!(
+			titles(
+				&Index::from_bytes(&bytes).unwrap().view(),
+				"k8s",
+				&SearchOptions::default()
+			),
+			vec!["Kubernetes Deployment"]
+		);
+	}
+
+	#[test]
+	fn test_synonyms_are_stemmed() {
+		let options = IndexOptions {
+			stemmer: Stemmer::Porter,
+			..synonym_options()
+		};
+		let index = build_index_with_options(synonym_documents(), &options).unwrap();
+
+		assert_eq!(
+			titles(&index.view(), "repos", &SearchOptions::default()),
+			vec!["Repository Settings"]
+		);
+	}
+
+	// ========================================================================
+	// SECTION 29: Custom Fields
+	// ========================================================================
+
+	fn custom_fields(fields: &[(&str, &str)]) -> BTreeMap<String, String> {
+		fields
+			.iter()
+			.map(|(name, value)| (name.to_string(), value.to_string()))
+			.collect()
+	}
+
+	fn custom_field_documents() -> Vec<Document> {
+		vec![
+			Document {
+				category: "Guides".to_string(),
+				fields: custom_fields(&[
+					("author", "Jane Doe"),
+					("product", "cli"),
+					("icon", "rocket"),
+					("internal", "draft"),
+				]),
+				..doc(
+					"Installing the CLI",
+					"/cli/install",
+					"Download the installer and run it",
+				)
+			},
+			Document {
+				category: "Guides".to_string(),
+				fields: custom_fields(&[("author", "John Smith"), ("product", "sdk")]),
+				..doc(
+					"Installing the SDK",
+					"/sdk/install",
+					"Add the package to your project",
+				)
+			},
+		]
+	}
+
+	fn custom_field_options() -> IndexOptions {
+		IndexOptions {
+			fields: vec![
+				crate::FieldSchema {
+					name: "author".to_string(),
+					searchable: true,
+					..Default::default()
+				},
+				crate::FieldSchema {
+					name: "product".to_string(),
+					filterable: true,
+					..Default::default()
+				},
+				crate::FieldSchema {
+					name: "icon".to_string(),
+					..Default::default()
+				},
+			],
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_custom_fields_are_returned() {
+		let index =
+			build_index_with_options(custom_field_documents(), &custom_field_options()).unwrap();
+
+		let results = search(&index.view(), "installing", 10).unwrap();
+		assert_eq!(results.len(), 2);
+		let cli = results
+			.iter()
+			.find(|hit| hit.document.as_ref().unwrap().href == "/cli/install")
+			.unwrap();
+		// fields without a schema are not stored
+		assert_eq!(
+			cli.document.as_ref().unwrap().fields,
+			custom_fields(&[
+				("author", "Jane Doe"),
+				("icon", "rocket"),
+				("product", "cli")
+			])
+		);
+
+		let bytes = index.to_bytes().unwrap();
+		let view = IndexView::from_bytes(&bytes).unwrap();
+		let document = get_document(&view, 1, &[Field::Custom]).unwrap();
+		assert_eq!(document.title, "");
+		assert_eq!(
+			document.fields,
+			custom_fields(&[("author", "John Smith"), ("product", "sdk")])
+		);
+	}
+
+	#[test]
+	fn test_searchable_custom_fields() {
+		let index =
+			build_index_with_options(custom_field_documents(), &custom_field_options()).unwrap();
+
+		let results = search(&index.view(), "jane", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/cli/install");
+		let results = search(&index.view(), "custom:smith", 10).unwrap();
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].document.as_ref().unwrap().href, "/sdk/install");
+
+		// stored-only fields are not searchable
+		assert!(search(&index.view(), "rocket", 10).unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_filter_custom_fields() {
+		let index =
+			build_index_with_options(custom_field_documents(), &custom_field_options()).unwrap();
+		let search_options = |filters: &[(&str, &[&str])]| SearchOptions {
+			filters: filters
+				.iter()
+				.map(|(name, values)| {
+					let values = values.iter().map(|value| value.to_string()).collect();
+					(name.to_string(), values)
+				})
+				.collect(),
+			..Default::default()
+		};
+
+		let results = search_with_options(
+			&index.view(),
+			"installing",
+			&search_options(&[("product", &["sdk"])]),
+		)
+		.unwrap();
+		assert_eq!(results.total, 1);
+		assert_eq!(results.hits[0].id, 1);
+
+		let options = search_options(&[("product", &["cli", "sdk"])]);
+		let results = search_with_options(&index.view(), "installing", &options).unwrap();
+		assert_eq!(results.total, 2);
+
+		let options = search_options(&[("product", &["docs"])]);
+		let results = search_with_options(&index.view(), "installing", &options).unwrap();
+		assert_eq!(results.total, 0);
+
+		let options = search_options(&[("author", &["Jane Doe"])]);
+		let error = search_with_options(&index.view(), "installing", &options).unwrap_err();
+		assert!(error.to_string().contains("'author'"));
+	}
+
+	// ========================================================================
+	// SECTION 30: Numeric and Date Filters
+	// ========================================================================
+
+	#[test]
+	fn test_parse_field_values() {
+		use crate::FieldKind;
+		assert_eq!(FieldKind::Number.parse(" 3.2 "), Some(3.2));
+		assert_eq!(FieldKind::Number.parse("3.2.1"), None);
+		assert_eq!(FieldKind::Date.parse("1970-01-01"), Some(0.0));
+		assert_eq!(FieldKind::Date.parse("1969-12-31"), Some(-1.0));
+		assert_eq!(FieldKind::Date.parse("2024-05-31"), Some(19874.0));
+		assert_eq!(FieldKind::Date.parse("2024-05-31T10:00:00Z"), Some(19874.0));
+		assert_eq!(FieldKind::Date.parse("2024-13-01"), None);
+		assert_eq!(FieldKind::Date.parse("2024-02-29"), Some(19782.0));
+		assert_eq!(FieldKind::Date.parse("2023-02-29"), None);
+		assert_eq!(FieldKind::Date.parse("2024-02-31"), None);
+		assert_eq!(FieldKind::Date.parse("2024-04-31"), None);
+		assert_eq!(FieldKind::Date.parse("May 2024"), None);
+		assert_eq!(FieldKind::Text.parse("3"), None);
+		// large numbers keep their precision
+		assert_eq!(FieldKind::Number.parse("16777217"), Some(16777217.0));
+
+		assert_eq!(FieldKind::version("3.10"), Some(vec![3, 10]));
+		assert_eq!(FieldKind::version("3.2.0"), Some(vec![3, 2]));
+		assert_eq!(FieldKind::version("0"), Some(vec![0]));
+		assert_eq!(FieldKind::version("3.x"), None);
+		assert!(FieldKind::version("3.10") > FieldKind::version("3.9"));
+	}
+
+	#[test]
+	fn test_parse_filter_expression() {
+		use crate::filter::{Comparison, Condition, parse};
+		let condition = |field: &str, comparison, value: &str| Condition {
+			field: field.to_string(),
+			comparison,
+			value: value.to_string(),
+		};
+
+		let filter = parse("version>=3.2 AND updated > 2024-01-01 OR product = \"cli tools\"").unwrap();
+		assert_eq!(
+			filter.groups,
+			vec![
+				vec![
+					condition("version", Comparison::GreaterOrEqual, "3.2"),
+					condition("updated", Comparison::Greater, "2024-01-01"),
+				],
+				vec![condition("product", Comparison::Equal, "cli tools")],
+			]
+		);
+
+		for invalid in [
+			"",
+			"version >=",
+			"version ~ 3",
+			"a = 1 XOR b = 2",
+			"a = \"open",
+		] {
+			assert!(parse(invalid).is_err(), "{}", invalid);
+		}
+	}
+
+	fn release_documents() -> Vec<Document> {
+		let release = |title: &str, fields: &[(&str, &str)]| Document {
+			category: "Releases".to_string(),
+			fields: custom_fields(fields),
+			..doc(
+				title,
+				&format!("/releases/{}", title.to_lowercase().replace(' ', "-")),
+				"Release notes for the extension host",
+			)
+		};
+		vec![
+			release(
+				"Release Maple",
+				&[
+					("version", "2.9"),
+					("updated", "2023-03-01"),
+					("product", "cli"),
+				],
+			),
+			release(
+				"Release Birch",
+				&[
+					("version", "3.2"),
+					("updated", "2024-06-15"),
+					("product", "cli"),
+				],
+			),
+			release(
+				"Release Cedar",
+				&[
+					("version", "4.0"),
+					("updated", "2025-01-10"),
+					("product", "sdk"),
+				],
+			),
+			release("Release Draft", &[]),
+		]
+	}
+
+	fn release_options() -> IndexOptions {
+		let field = |name: &str, kind| crate::FieldSchema {
+			name: name.to_string(),
+			filterable: true,
+			sortable: true,
+			kind,
+			..Default::default()
+		};
+		IndexOptions {
+			fields: vec![
+				field("version", crate::FieldKind::Version),
+				field("updated", crate::FieldKind::Date),
+				field("product", crate::FieldKind::Text),
+			],
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn test_filter_numbers_and_dates() {
+		let index = build_index_with_options(release_documents(), &release_options()).unwrap();
+
+		for (filter, expected) in [
+			("version >= 3.2", vec!["Release Birch", "Release Cedar"]),
+			("updated < 2024-06-15", vec!["Release Maple"]),
+			(
+				"version > 3 AND product = cli OR updated < 2024-01-01",
+				vec!["Release Maple", "Release Birch"],
+			),
+			// documents without a value only match `!=`
+			(
+				"version != 4",
+				vec!["Release Maple", "Release Birch", "Release Draft"],
+			),
+			(
+				"product != sdk",
+				vec!["Release Maple", "Release Birch", "Release Draft"],
+			),
+		] {
+			let options = SearchOptions {
+				filter: Some(filter.to_string()),
+				..Default::default()
+			};
+			assert_eq!(
+				titles(&index.view(), "release", &options),
+				expected,
+				"{}",
+				filter
+			);
+		}
+	}
+
+	#[test]
+	fn test_filter_versions_by_part() {
+		let mut documents = release_documents();
+		documents[3].fields = custom_fields(&[("version", "3.10")]);
+		let index = build_index_with_options(documents, &release_options()).unwrap();
+
+		for (filter, expected) in [
+			// 3.10 is newer than 3.2, unlike the decimal number
+			(
+				"version >= 3.2",
+				vec!["Release Birch", "Release Cedar", "Release Draft"],
+			),
+			("version < 3.10", vec!["Release Maple", "Release Birch"]),
+			// versions no document has fall between the stored ones
+			("version > 3.9.1 AND version < 3.11", vec!["Release Draft"]),
+			("version = 3.10.0", vec!["Release Draft"]),
+			("version = 3.3", vec![]),
+		] {
+			let options = SearchOptions {
+				filter: Some(filter.to_string()),
+				..Default::default()
+			};
+			assert_eq!(
+				titles(&index.view(), "release", &options),
+				expected,
+				"{}",
+				filter
+			);
+		}
+	}
+
+	#[test]
+	fn test_filter_prunes_before_truncation() {
+		let index = build_index_with_options(release_documents(), &release_options()).unwrap();
+		let options = SearchOptions {
+			max_results: 1,
+			filter: Some("updated >= 2025-01-01".to_string()),
+			..Default::default()
+		};
+		let results = search_with_options(&index.view(), "release", &options).unwrap();
+		assert_eq!(results.total, 1);
+		assert_eq!(
+			results.hits[0].document.as_ref().unwrap().title,
+			"Release Cedar"
+		);
+
+		// filters survive serialization
+		let bytes = index.to_bytes().unwrap();
+		let view = IndexView::from_bytes(&bytes).unwrap();
+		let results = search_with_options(&view, "release", &options).unwrap();
+		assert_eq!(results.total, 1);
+	}
+
+	#[test]
+	fn test_document_fields_from_json() {
+		let json = r#"{"title": "Release Five", "category": "Releases", "href": "/five", "body": "",
+			"fields": {"version": 3.2, "build": 42, "beta": true, "product": "cli"}}"#;
+		let document: Document = serde_json::from_str(json).unwrap();
+		assert_eq!(
+			document.fields,
+			custom_fields(&[
+				("beta", "true"),
+				("build", "42"),
+				("product", "cli"),
+				("version", "3.2"),
+			])
+		);
+
+		// values are written back as strings, which read the same
+		let json = serde_json::to_string(&document).unwrap();
+		let read_back: Document = serde_json::from_str(&json).unwrap();
+		assert_eq!(read_back.fields, document.fields);
+
+		// and filter like the strings would
+		let index = build_index_with_options(vec![document], &release_options()).unwrap();
+		let options = SearchOptions {
+			filter: Some("version > 3 AND product = cli".to_string()),
+			..Default::default()
+		};
+		assert_eq!(
+			titles(&index.view(), "release", &options),
+			vec!["Release Five"]
+		);
+
+		let json = r#"{"title": "Release Five", "category": "Releases", "href": "/five", "body": "", "fields": {"tags": ["x"]}}"#;
+		assert!(serde_json::from_str::<Document>(json).is_err());
+	}
+
+	#[test]
+	fn test_filter_errors() {
+		let index = build_index_with_options(release_documents(), &release_options()).unwrap();
+		for (filter, message) in [
+			("author = jane", "'author'"),
+			("product > cli", "'product'"),
+			("version > latest", "'latest'"),
+		] {
+			let options = SearchOptions {
+				filter: Some(filter.to_string()),
+				..Default::default()
+			};
+			let error = search_with_options(&index.view(), "release", &options).unwrap_err();
+			assert!(error.to_string().contains(message), "{}", error);
+		}
+
+		let mut documents = release_documents();
+		documents[0]
+			.fields
+			.insert("version".to_string(), "2.x".to_string());
+		let error = build_index_with_options(documents, &release_options()).unwrap_err();
+		assert!(error.to_string().contains("'2.x'"));
+	}
+
+	// ========================================================================
+	// SECTION 31: Sorting
+	// ========================================================================
+
+	#[test]
+	fn test_sort_by_attribute() {
+		let index = build_index_with_options(release_documents(), &release_options()).unwrap();
+
+		for (query, sort, expected) in [
+			(
+				"release",
+				vec![("updated", true)],
+				[
+					"Release Cedar",
+					"Release Birch",
+					"Release Maple",
+					"Release Draft",
+				],
+			),
+			// numbers are not ordered as text, and missing values still come last
+			(
+				"release",
+				vec![("version", false)],
+				[
+					"Release Maple",
+					"Release Birch",
+					"Release Cedar",
+					"Release Draft",
+				],
+			),
+			(
+				"release",
+				vec![("title", false)],
+				[
+					"Release Birch",
+					"Release Cedar",
+					"Release Draft",
+					"Release Maple",
+				],
+			),
+		] {
+			let options = SearchOptions {
+				sort: sort
+					.iter()
+					.map(|(field, descending)| crate::SortKey {
+						field: field.to_string(),
+						descending: *descending,
+					})
+					.collect(),
+				..Default::default()
+			};
+			assert_eq!(
+				titles(&index.view(), query, &options),
+				expected,
+				"{:?}",
+				sort
+			);
+		}
+	}
+
+	#[test]
+	fn test_sort_ties_ranked_by_relevance() {
+		let index = build_index_with_options(release_documents(), &release_options()).unwrap();
+
+		for (query, sort, expected) in [
+			(
+				"release",
+				vec![("product", false)],
+				[
+					"Release Maple",
+					"Release Birch",
+					"Release Cedar",
+					"Release Draft",
+				],
+			),
+			(
+				"release birch",
+				vec![("product", false)],
+				[
+					"Release Birch",
+					"Release Maple",
+					"Release Cedar",
+					"Release Draft",
+				],
+			),
+			(
+				"release",
+				vec![("product", true), ("updated", false)],
+				[
+					"Release Cedar",
+					"Release Maple",
+					"Release Birch",
+					"Release Draft",
+				],
+			),
+		] {
+			let options = SearchOptions {
+				sort: sort
+					.iter()
+					.map(|(field, descending)| crate::SortKey {
+						field: field.to_string(),
+						descending: *descending,
+					})
+					.collect(),
+				..Default::default()
+			};
+			assert_eq!(
+				titles(&index.view(), query, &options),
+				expected,
+				"{:?}",
+				sort
+			);
+		}
+	}
+
+	#[test]
+	fn test_sort_pages_and_serialization() {
+		let index = build_index_with_options(release_documents(), &release_options()).unwrap();
+		let bytes = index.to_bytes().unwrap();
+		let view = IndexView::from_bytes(&bytes).unwrap();
+
+		let options = SearchOptions {
+			max_results: 2,
+			offset: 1,
+			sort: vec![crate::SortKey {
+				field: "updated".to_string(),
+				descending: true,
+			}],
+			..Default::default()
+		};
+		assert_eq!(
+			titles(&view, "release", &options),
+			vec!["Release Birch", "Release Maple"]
+		);
+
+		let options = SearchOptions {
+			sort: vec![crate::SortKey {
+				field: "body".to_string(),
+				descending: false,
+			}],
+			..Default::default()
+		};
+		let error = search_with_options(&view, "release", &options).unwrap_err();
+		assert!(error.to_string().contains("'body'"));
+	}
+
+	// ========================================================================
+	// SECTION 32: Document Popularity
+	// ========================================================================
+
+	fn popular_documents() -> Vec<Document> {
+		let document = |title: &str, body: &str, popularity: Option<f64>| Document {
+			category: "Guides".to_string(),
+			popularity,
+			..doc(
+				title,
+				&format!("/{}", title.to_lowercase().replace(' ', "-")),
+				body,
+			)
+		};
+		vec![
+			document("Extension Basics", "Niche details", Some(10.0)),
+			document("Extension Marketplace", "Landing page", Some(25000.0)),
+			document("Publishing", "Package your extension", Some(1e9)),
+			document("Extension Samples", "More samples", None),
+		]
+	}
+
+	#[test]
+	fn test_popularity_breaks_keyword_ties() {
+		let index = build_index(popular_documents()).unwrap();
+		assert_eq!(
+			titles(&index.view(), "extension", &SearchOptions::default()),
+			vec![
+				"Extension Marketplace",
+				"Extension Basics",
+				"Extension Samples",
+				"Publishing"
+			]
+		);
+
+		// without weight, equal scores fall back to document order
+		let options = SearchOptions {
+			popularity_weight: 0.0,
+			..Default::default()
+		};
+		assert_eq!(
+			titles(&index.view(), "extension", &options),
+			vec![
+				"Extension Basics",
+				"Extension Marketplace",
+				"Extension Samples",
+				"Publishing"
+			]
+		);
+	}
+
+	#[test]
+	fn test_popularity_scales_scores() {
+		let index = build_index(popular_documents()).unwrap();
+		let bytes = index.to_bytes().unwrap();
+		let view = IndexView::from_bytes(&bytes).unwrap();
+
+		let unweighted = SearchOptions {
+			popularity_weight: 0.0,
+			..Default::default()
+		};
+		let base = search_with_options(&view, "publishing", &unweighted).unwrap();
+		let boosted = search_with_options(&view, "publishing", &SearchOptions::default()).unwrap();
+		assert!((boosted.hits[0].score - base.hits[0].score * 1.5).abs() < 1e-3);
+
+		// documents without a popularity keep their score
+		let base = search_with_options(&view, "samples", &unweighted).unwrap();
+		let boosted = search_with_options(&view, "samples", &SearchOptions::default()).unwrap();
+		assert_eq!(boosted.hits[0].score, base.hits[0].score);
+	}
+
+	#[test]
+	fn test_document_popularity_from_json() {
+		let json = r#"{"title": "A", "category": "B", "href": "/a", "body": "", "popularity": 1200}"#;
+		let document: Document = serde_json::from_str(json).unwrap();
+		assert_eq!(document.popularity, Some(1200.0));
+	}
+
+	// ========================================================================
+	// SECTION 33: Query-Time Field Boosts
+	// ========================================================================
+
+	fn boosted_documents() -> Vec<Document> {
+		let document = |title: &str, href: &str, body: &str| Document {
+			category: "Docs".to_string(),
+			..doc(title, href, body)
+		};
+		vec![
+			document("Deploying Workers", "/workers", "Configure the runtime."),
+			document(
+				"Runtime Overview",
+				"/runtime",
+				"Workers handle requests close to your users.",
+			),
+		]
+	}
+
+	#[test]
+	fn test_field_boosts_favour_title_or_body() {
+		let index = build_index(boosted_documents()).unwrap();
+		let bytes = index.to_bytes().unwrap();
+		let view = IndexView::from_bytes(&bytes).unwrap();
+
+		let typeahead = SearchOptions {
+			field_boosts: BTreeMap::from([(Field::Title, 10.0), (Field::Body, 0.1)]),
+			..Default::default()
+		};
+		assert_eq!(
+			titles(&view, "runtime", &typeahead),
+			vec!["Runtime Overview", "Deploying Workers"]
+		);
+
+		let results_page = SearchOptions {
+			field_boosts: BTreeMap::from([(Field::Title, 0.1), (Field::Body, 10.0)]),
+			..Default::default()
+		};
+		assert_eq!(
+			titles(&view, "runtime", &results_page),
+			vec!["Deploying Workers", "Runtime Overview"]
+		);
+	}
+
+	#[test]
+	fn test_field_boosts_scale_scores() {
+		let index = build_index(boosted_documents()).unwrap();
+		let view = index.view();
+
+		let base = search_with_options(&view, "deploying", &SearchOptions::default()).unwrap();
+		let options = SearchOptions {
+			field_boosts: BTreeMap::from([(Field::Title, 2.0)]),
+			..Default::default()
+		};
+		let boosted = search_with_options(&view, "deploying", &options).unwrap();
+		assert!((boosted.hits[0].score - base.hits[0].score * 2.0).abs() < 1e-3);
+
+		// boosting another field leaves the score alone
+		let options = SearchOptions {
+			field_boosts: BTreeMap::from([(Field::Body, 2.0)]),
+			..Default::default()
+		};
+		let other = search_with_options(&view, "deploying", &options).unwrap();
+		assert_eq!(other.hits[0].score, base.hits[0].score);
+	}
+
+	#[test]
+	fn test_field_boosts_with_bm25() {
+		let options = IndexOptions {
+			ranking: Ranking::Bm25,
+			..Default::default()
+		};
+		let index = build_index_with_options(boosted_documents(), &options).unwrap();
+		let view = index.view();
+
+		let typeahead = SearchOptions {
+			field_boosts: BTreeMap::from([(Field::Title, 10.0), (Field::Body, 0.1)]),
+			..Default::default()
+		};
+		assert_eq!(
+			titles(&view, "runtime", &typeahead),
+			vec!["Runtime Overview", "Deploying Workers"]
+		);
+
+		let results_page = SearchOptions {
+			field_boosts: BTreeMap::from([(Field::Title, 0.1), (Field::Body, 10.0)]),
+			..Default::default()
+		};
+		assert_eq!(
+			titles(&view, "runtime", &results_page),
+			vec!["Deploying Workers", "Runtime Overview"]
+		);
+	}
+
+	#[test]
+	fn test_field_boosts_use_matched_field() {
+		let mut documents = boosted_documents();
+		documents[1].category = "Runtime".to_string();
+		let index = build_index(documents).unwrap();
+		let view = index.view();
+		let search = |boosts: &[(Field, f32)]| {
+			let options = SearchOptions {
+				field_boosts: boosts.iter().copied().collect(),
+				..Default::default()
+			};
+			let results = search_with_options(&view, "category:runtime", &options).unwrap();
+			assert_eq!(results.total, 1);
+			results.hits[0].score
+		};
+
+		// the keyword was taken from the title, but only its category matched
+		let base = search(&[]);
+		assert!(base > 0.0);
+		assert_eq!(search(&[(Field::Title, 0.0)]), base);
+		assert!((search(&[(Field::Category, 2.0)]) - base * 2.0).abs() < 1e-3);
+	}
+
+	#[test]
+	fn test_field_boosts_from_json() {
+		let json = r#"{"fieldBoosts": {"title": 3, "body": 0.5}}"#;
+		let options: SearchOptions = serde_json::from_str(json).unwrap();
+		assert_eq!(
+			options.field_boosts,
+			BTreeMap::from([(Field::Title, 3.0), (Field::Body, 0.5)])
+		);
+	}
+
+	#[test]
+	fn test_posting_keeps_origin() {
+		let index = build_index(boosted_documents()).unwrap();
+		let view = index.view();
+		let map = fst::Map::new(view.fst).unwrap();
+		let keyword_index = map.get("deploying").unwrap() as usize;
+		let postings: Vec<Posting> = view.postings(keyword_index).collect();
+		assert_eq!(postings.len(), 1);
+		assert_eq!(postings[0].origin, Field::Title);
+	}
 }
@@ -0,0 +1,132 @@
+//! Splitting text into normalized words, shared by indexing and searching so that
+//! both sides agree on what a word is.
+//!
+//! Words are found with Unicode word segmentation (UAX #29) and folded to lowercase
+//! without diacritics, so that "Café" matches "cafe". Chinese and Japanese text
+//! has no spaces between words, runs of those characters are indexed as
+//! overlapping bigrams instead.
+//!
+//! Code identifiers are additionally split into their parts, see [`identifier`].
+
+use std::ops::Range;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Whether `c` belongs to a script written without spaces between words.
+pub(crate) fn is_cjk(c: char) -> bool {
+	matches!(c,
+		'\u{3040}'..='\u{30FF}' // Hiragana and Katakana
+		| '\u{31F0}'..='\u{31FF}' // Katakana phonetic extensions
+		| '\u{3400}'..='\u{4DBF}' // CJK unified ideographs extension A
+		| '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+		| '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+		| '\u{FF66}'..='\u{FF9F}' // Halfwidth Katakana
+		| '\u{20000}'..='\u{2FA1F}' // Supplementary ideographs
+	)
+}
+
+/// Fold a word to lowercase, decomposing compatibility characters such as
+/// ligatures and removing diacritics.
+pub(crate) fn fold(word: &str) -> String {
+	word
+		.nfkd()
+		.filter(|c| !is_combining_mark(*c))
+		.collect::<String>()
+		.to_lowercase()
+}
+
+/// Turn a run of consecutive CJK characters into bigrams, or a single character
+/// into a word of its own. The characters keep their diacritics, which change
+/// the meaning of Kana.
+fn push_bigrams(run: &mut Vec<(usize, char)>, tokens: &mut Vec<(Range<usize>, String)>) {
+	let end = |(i, c): (usize, char)| i + c.len_utf8();
+	match run.as_slice() {
+		[] => {}
+		[single] => tokens.push((
+			single.0..end(*single),
+			single.1.to_string().nfkc().collect(),
+		)),
+		pairs => tokens.extend(pairs.windows(2).map(|pair| {
+			let text = [pair[0].1, pair[1].1].into_iter().nfkc().collect();
+			(pair[0].0..end(pair[1]), text)
+		})),
+	}
+	run.clear();
+}
+
+/// Split `text` into normalized words, in order of appearance, along with the
+/// bytes of `text` each word was read from.
+pub(crate) fn tokens(text: &str) -> Vec<(Range<usize>, String)> {
+	let mut tokens = Vec::new();
+	let mut run: Vec<(usize, char)> = Vec::new();
+
+	for (start, word) in text.unicode_word_indices() {
+		if word.chars().all(is_cjk) {
+			// segmentation splits ideographs apart, join them back when adjacent
+			if run.last().is_some_and(|&(i, c)| i + c.len_utf8() != start) {
+				push_bigrams(&mut run, &mut tokens);
+			}
+			run.extend(word.char_indices().map(|(i, c)| (start + i, c)));
+			continue;
+		}
+
+		push_bigrams(&mut run, &mut tokens);
+		let text = fold(word);
+		if !text.is_empty() {
+			tokens.push((start..start + word.len(), text));
+		}
+	}
+	push_bigrams(&mut run, &mut tokens);
+
+	tokens
+}
+
+/// Split `text` into normalized words, without their positions.
+pub(crate) fn words(text: &str) -> Vec<String> {
+	tokens(text).into_iter().map(|(_, word)| word).collect()
+}
+
+/// Split a segment of an identifier on case changes, e.g. `getHTTPServer` into
+/// `get`, `HTTP` and `Server`.
+fn split_camel_case(segment: &str) -> Vec<&str> {
+	let chars: Vec<(usize, char)> = segment.char_indices().collect();
+	let mut parts = Vec::new();
+	let mut start = 0;
+	for i in 1..chars.len() {
+		let (prev, (at, c)) = (chars[i - 1].1, chars[i]);
+		let next_lower = chars
+			.get(i + 1)
+			.is_some_and(|(_, next)| next.is_lowercase());
+		if c.is_uppercase()
+			&& (prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_lower))
+		{
+			parts.push(&segment[start..at]);
+			start = at;
+		}
+	}
+	parts.push(&segment[start..]);
+	parts
+}
+
+/// Recognize a code identifier such as `getUserById`, `max_retry_count` or
+/// `std::fs::read_to_string`, returning the whole identifier and its parts,
+/// all normalized. Parts of a single character are left out.
+pub(crate) fn identifier(word: &str) -> Option<(String, Vec<String>)> {
+	let is_separator = |c: char| matches!(c, '_' | '.' | ':' | '/');
+	let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+	if !word.chars().all(|c| c.is_alphanumeric() || is_separator(c)) {
+		return None;
+	}
+
+	let parts: Vec<String> = word
+		.split(is_separator)
+		.flat_map(split_camel_case)
+		.map(fold)
+		.filter(|part| part.chars().count() > 1)
+		.collect();
+	if parts.len() < 2 {
+		return None;
+	}
+	Some((fold(word), parts))
+}
@@ -1,4 +1,8 @@
-import _init, { search as _search } from './pkg/docfind.js';
+import _init, {
+  search as _search,
+  searchWithOptions as _searchWithOptions,
+  getDocument as _getDocument,
+} from './pkg/docfind.js';
 
 let didInit = false;
 
@@ -6,10 +10,24 @@ export function init() {
   return _init();
 }
 
-export default async function search(needle, maxResults) {
+async function ensureInit() {
   if (!didInit) {
     await _init();
     didInit = true;
   }
-  return _search(needle, maxResults);
-}
\ No newline at end of file
+}
+
+export default async function search(needle, maxResults, offset, filter) {
+  await ensureInit();
+  return _search(needle, maxResults, offset, filter);
+}
+
+export async function searchWithOptions(needle, options) {
+  await ensureInit();
+  return _searchWithOptions(needle, options);
+}
+
+export async function getDocument(id, fields) {
+  await ensureInit();
+  return _getDocument(id, fields);
+}
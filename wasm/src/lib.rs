@@ -1,4 +1,4 @@
-use docfind_core::Index;
+use docfind_core::{Field, IndexView, SearchOptions};
 use std::sync::OnceLock;
 use wasm_bindgen::prelude::*;
 
@@ -14,21 +14,79 @@ pub static mut INDEX_BASE: u32 = 0xdead_beef;
 #[unsafe(no_mangle)]
 pub static mut INDEX_LEN: u32 = 0xdead_beef;
 
-static INDEX: OnceLock<Index> = OnceLock::new();
+static INDEX: OnceLock<IndexView<'static>> = OnceLock::new();
 
-/// Search the index for a query string
-/// Returns a JavaScript array of matching documents
-#[wasm_bindgen]
-pub fn search(query: &str, max_results: Option<usize>) -> Result<JsValue, JsValue> {
-	let index = INDEX.get_or_init(|| {
+fn index() -> &'static IndexView<'static> {
+	INDEX.get_or_init(|| {
 		let raw_index =
 			unsafe { std::slice::from_raw_parts(INDEX_BASE as *const u8, INDEX_LEN as usize) };
-		Index::from_bytes(raw_index).expect("Failed to deserialize index")
-	});
+		IndexView::from_bytes(raw_index).expect("Failed to read index")
+	})
+}
+
+/// Search the index for a query string, skipping the first `offset` hits and
+/// optionally restricted by a filter expression such as
+/// `version >= 3.2 AND updated > 2024-01-01`
+/// Returns a JavaScript object with a page of search hits, each holding the matching
+/// document, its score and the index keywords it matched, and the total hit count
+#[wasm_bindgen]
+pub fn search(
+	query: &str,
+	max_results: Option<usize>,
+	offset: Option<usize>,
+	filter: Option<String>,
+) -> Result<JsValue, JsValue> {
+	let options = SearchOptions {
+		max_results: max_results.unwrap_or(10),
+		offset: offset.unwrap_or(0),
+		filter,
+		..Default::default()
+	};
+	let result = docfind_core::search_with_options(index(), query, &options)
+		.map_err(|e| JsValue::from_str(&format!("Search failed: {}", e)))?;
+
+	serde_wasm_bindgen::to_value(&result)
+		.map_err(|e| JsValue::from_str(&format!("Failed to convert results to JS: {}", e)))
+}
 
-	let result = docfind_core::search(index, query, max_results.unwrap_or(10))
+/// Search the index with an options object, e.g. `{ maxResults, offset, categories, facets, snippet, mode }`
+/// Returns a JavaScript object with a page of search hits, the total hit count and category facets
+#[wasm_bindgen(js_name = searchWithOptions)]
+pub fn search_with_options(query: &str, options: JsValue) -> Result<JsValue, JsValue> {
+	let options: SearchOptions = if options.is_undefined() || options.is_null() {
+		SearchOptions::default()
+	} else {
+		serde_wasm_bindgen::from_value(options)
+			.map_err(|e| JsValue::from_str(&format!("Invalid search options: {}", e)))?
+	};
+
+	let result = docfind_core::search_with_options(index(), query, &options)
 		.map_err(|e| JsValue::from_str(&format!("Search failed: {}", e)))?;
 
 	serde_wasm_bindgen::to_value(&result)
 		.map_err(|e| JsValue::from_str(&format!("Failed to convert results to JS: {}", e)))
 }
+
+/// Decode a document by the id of a search hit
+/// Only the requested fields, e.g. `["title", "href"]`, are decoded; all of them if omitted
+#[wasm_bindgen(js_name = getDocument)]
+pub fn get_document(id: usize, fields: JsValue) -> Result<JsValue, JsValue> {
+	let fields: Vec<Field> = if fields.is_undefined() || fields.is_null() {
+		vec![
+			Field::Title,
+			Field::Category,
+			Field::Href,
+			Field::Body,
+			Field::Custom,
+		]
+	} else {
+		serde_wasm_bindgen::from_value(fields)
+			.map_err(|e| JsValue::from_str(&format!("Invalid document fields: {}", e)))?
+	};
+
+	let document = docfind_core::get_document(index(), id, &fields)
+		.map_err(|e| JsValue::from_str(&format!("Failed to get document: {}", e)))?;
+
+	serde_wasm_bindgen::to_value(&document)
+		.map_err(|e| JsValue::from_str(&format!("Failed to convert document to JS: {}", e)))
+}